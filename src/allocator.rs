@@ -0,0 +1,115 @@
+//! the allocator used by `Trailer`
+//!
+//! with the `allocator-api2` feature, any `allocator_api2::alloc::Allocator`
+//! can be used. Otherwise, this is a minimal version of the same trait that
+//! is only implemented by the global allocator.
+
+#[cfg(feature = "allocator-api2")]
+pub use allocator_api2::alloc::{Allocator, Global};
+
+#[cfg(not(feature = "allocator-api2"))]
+pub use self::fallback::{Allocator, Global};
+
+#[cfg(not(feature = "allocator-api2"))]
+mod fallback {
+    use std::{
+        alloc::{self, Layout},
+        ptr::NonNull,
+    };
+
+    pub struct AllocError;
+
+    /// the subset of `allocator_api2::alloc::Allocator` used by `Trailer`
+    ///
+    /// # Safety
+    ///
+    /// same contract as `allocator_api2::alloc::Allocator`
+    pub unsafe trait Allocator {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError>;
+
+        fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError>;
+
+        /// # Safety
+        ///
+        /// `ptr` must have been allocated by this allocator with `layout`
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout);
+
+        /// # Safety
+        ///
+        /// `ptr` must have been allocated by this allocator with
+        /// `old_layout`, which must not be larger than `new_layout`
+        unsafe fn grow(
+            &self,
+            ptr: NonNull<u8>,
+            old_layout: Layout,
+            new_layout: Layout,
+        ) -> Result<NonNull<[u8]>, AllocError>;
+
+        /// # Safety
+        ///
+        /// `ptr` must have been allocated by this allocator with
+        /// `old_layout`, which must not be smaller than `new_layout`
+        unsafe fn shrink(
+            &self,
+            ptr: NonNull<u8>,
+            old_layout: Layout,
+            new_layout: Layout,
+        ) -> Result<NonNull<[u8]>, AllocError>;
+    }
+
+    /// the global memory allocator
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct Global;
+
+    unsafe fn realloc(
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        debug_assert_eq!(old_layout.align(), new_layout.align());
+        block(
+            alloc::realloc(ptr.as_ptr(), old_layout, new_layout.size()),
+            new_layout.size(),
+        )
+    }
+
+    fn block(ptr: *mut u8, size: usize) -> Result<NonNull<[u8]>, AllocError> {
+        NonNull::new(ptr)
+            .map(|ptr| NonNull::slice_from_raw_parts(ptr, size))
+            .ok_or(AllocError)
+    }
+
+    // every layout used by `Trailer` has a non zero size, since it starts
+    // with the capacity
+    unsafe impl Allocator for Global {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            block(unsafe { alloc::alloc(layout) }, layout.size())
+        }
+
+        fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            block(unsafe { alloc::alloc_zeroed(layout) }, layout.size())
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            alloc::dealloc(ptr.as_ptr(), layout)
+        }
+
+        unsafe fn grow(
+            &self,
+            ptr: NonNull<u8>,
+            old_layout: Layout,
+            new_layout: Layout,
+        ) -> Result<NonNull<[u8]>, AllocError> {
+            realloc(ptr, old_layout, new_layout)
+        }
+
+        unsafe fn shrink(
+            &self,
+            ptr: NonNull<u8>,
+            old_layout: Layout,
+            new_layout: Layout,
+        ) -> Result<NonNull<[u8]>, AllocError> {
+            realloc(ptr, old_layout, new_layout)
+        }
+    }
+}
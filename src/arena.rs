@@ -0,0 +1,342 @@
+use std::{
+    alloc::{self, Layout},
+    cell::RefCell,
+    cmp, fmt,
+    marker::PhantomData,
+    mem,
+    ops::{Deref, DerefMut},
+    ptr::{self, NonNull},
+};
+
+use crate::Trailer;
+
+const DEFAULT_CHUNK_SIZE: usize = 4096;
+const CHUNK_ALIGN: usize = 16;
+
+/// a header of type `T` followed by bytes, allocated in a `TrailerArena`
+///
+/// it is laid out like the header and tail of a `Trailer<T>`, and is only
+/// accessed through references given by the arena
+#[repr(C)]
+pub struct ArenaTrailer<T> {
+    header: T,
+    bytes: [u8],
+}
+
+impl<T> ArenaTrailer<T> {
+    pub fn capacity(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.bytes
+    }
+
+    /// the header and the tail, mutably borrowed at the same time
+    pub fn parts_mut(&mut self) -> (&mut T, &mut [u8]) {
+        (&mut self.header, &mut self.bytes)
+    }
+
+    /// copies the header and the tail to a `Trailer` that can outlive the
+    /// arena
+    pub fn to_trailer(&self) -> Trailer<T>
+    where
+        T: Clone,
+    {
+        Trailer::new_with(self.capacity(), |bytes| {
+            bytes.copy_from_slice(&self.bytes);
+            self.header.clone()
+        })
+    }
+}
+
+impl<T> Deref for ArenaTrailer<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.header
+    }
+}
+
+impl<T> DerefMut for ArenaTrailer<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.header
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ArenaTrailer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ArenaTrailer")
+            .field("header", &self.header)
+            .field("bytes", &&self.bytes)
+            .finish()
+    }
+}
+
+/// allocates many trailers in a few large chunks, and frees them all at once
+///
+/// headers that need dropping are dropped by `reset` or when the arena is
+/// dropped. The `'h` lifetime bounds the data borrowed by those headers, so
+/// that it is still valid at that point
+///
+/// `'h` is invariant, so a header cannot borrow data that dies before the
+/// arena:
+///
+/// ```compile_fail
+/// use trailer::TrailerArena;
+///
+/// struct D<'a>(&'a String);
+///
+/// impl<'a> Drop for D<'a> {
+///     fn drop(&mut self) {
+///         println!("{}", self.0);
+///     }
+/// }
+///
+/// let arena = TrailerArena::new();
+/// {
+///     let s = String::from("borrowed");
+///     arena.alloc(D(&s), 0);
+/// }
+/// drop(arena);
+/// ```
+pub struct TrailerArena<'h> {
+    state: RefCell<ArenaState>,
+    // invariant, otherwise `alloc` could be called through a reborrow with a
+    // shorter `'h`
+    phantom: PhantomData<fn(&'h ()) -> &'h ()>,
+}
+
+struct ArenaState {
+    chunks: Vec<Chunk>,
+    // the chunk currently used, and the offset of its first free byte
+    current: usize,
+    offset: usize,
+    chunk_size: usize,
+    drops: Vec<PendingDrop>,
+}
+
+struct Chunk {
+    ptr: NonNull<u8>,
+    layout: Layout,
+}
+
+struct PendingDrop {
+    header: *mut u8,
+    drop: unsafe fn(*mut u8),
+}
+
+unsafe fn drop_header<T>(header: *mut u8) {
+    ptr::drop_in_place(header as *mut T);
+}
+
+impl<'h> TrailerArena<'h> {
+    pub fn new() -> TrailerArena<'h> {
+        TrailerArena::with_chunk_size(DEFAULT_CHUNK_SIZE)
+    }
+
+    /// the arena allocates chunks of at least `chunk_size` bytes, doubling
+    /// the size for each new chunk
+    pub fn with_chunk_size(chunk_size: usize) -> TrailerArena<'h> {
+        TrailerArena {
+            state: RefCell::new(ArenaState {
+                chunks: Vec::new(),
+                current: 0,
+                offset: 0,
+                chunk_size: cmp::max(chunk_size, 1),
+                drops: Vec::new(),
+            }),
+            phantom: PhantomData,
+        }
+    }
+
+    /// allocates a trailer with a zeroed tail of `capacity` bytes
+    // every call returns a different part of the chunks
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc<T: 'h>(&self, t: T, capacity: usize) -> &mut ArenaTrailer<T> {
+        let (layout, header_offset, _) =
+            Trailer::<T>::layout(capacity).unwrap_or_else(|e| e.handle());
+        // references to the `ArenaTrailer` cover its size rounded up to the
+        // header alignment
+        let layout = layout.pad_to_align();
+
+        let mut state = self.state.borrow_mut();
+        let base = state.bump(layout);
+        unsafe {
+            (base.as_ptr() as *mut usize).write(capacity);
+            let header = base.as_ptr().add(header_offset);
+            (header as *mut T).write(t);
+            ptr::write_bytes(header.add(mem::size_of::<T>()), 0, capacity);
+
+            if mem::needs_drop::<T>() {
+                state.drops.push(PendingDrop {
+                    header,
+                    drop: drop_header::<T>,
+                });
+            }
+
+            &mut *(ptr::slice_from_raw_parts_mut(header, capacity) as *mut ArenaTrailer<T>)
+        }
+    }
+
+    /// drops the headers and makes the memory available for new trailers
+    pub fn reset(&mut self) {
+        let state = self.state.get_mut();
+        state.drop_headers();
+        state.current = 0;
+        state.offset = 0;
+    }
+
+    /// total size of the chunks allocated by the arena
+    pub fn allocated_bytes(&self) -> usize {
+        self.state
+            .borrow()
+            .chunks
+            .iter()
+            .map(|chunk| chunk.layout.size())
+            .sum()
+    }
+}
+
+impl<'h> Default for TrailerArena<'h> {
+    fn default() -> Self {
+        TrailerArena::new()
+    }
+}
+
+impl ArenaState {
+    fn bump(&mut self, layout: Layout) -> NonNull<u8> {
+        loop {
+            if let Some(chunk) = self.chunks.get(self.current) {
+                let start = chunk.ptr.as_ptr() as usize + self.offset;
+                let padding = start.wrapping_neg() & (layout.align() - 1);
+                let available = chunk.layout.size() - self.offset;
+                // cannot overflow: a layout size is at most `isize::MAX`
+                // minus its alignment
+                if padding + layout.size() <= available {
+                    let ptr = unsafe { chunk.ptr.as_ptr().add(self.offset + padding) };
+                    self.offset += padding + layout.size();
+                    return unsafe { NonNull::new_unchecked(ptr) };
+                }
+
+                self.current += 1;
+                self.offset = 0;
+                continue;
+            }
+
+            // no chunk left with enough space
+            let needed = layout
+                .size()
+                .checked_add(layout.align())
+                .expect("capacity overflow");
+            let size = cmp::max(self.chunk_size, needed);
+            let chunk_layout =
+                Layout::from_size_align(size, CHUNK_ALIGN).expect("capacity overflow");
+            let ptr = NonNull::new(unsafe { alloc::alloc(chunk_layout) })
+                .unwrap_or_else(|| alloc::handle_alloc_error(chunk_layout));
+            self.chunks.push(Chunk {
+                ptr,
+                layout: chunk_layout,
+            });
+            self.current = self.chunks.len() - 1;
+            self.offset = 0;
+            self.chunk_size = self.chunk_size.saturating_mul(2);
+        }
+    }
+
+    fn drop_headers(&mut self) {
+        for pending in mem::take(&mut self.drops) {
+            unsafe { (pending.drop)(pending.header) };
+        }
+    }
+}
+
+impl<'h> Drop for TrailerArena<'h> {
+    fn drop(&mut self) {
+        let state = self.state.get_mut();
+        state.drop_headers();
+        for chunk in state.chunks.drain(..) {
+            unsafe { alloc::dealloc(chunk.ptr.as_ptr(), chunk.layout) };
+        }
+    }
+}
+
+impl<'h> fmt::Debug for TrailerArena<'h> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TrailerArena")
+            .field("allocated_bytes", &self.allocated_bytes())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::Cell, rc::Rc};
+
+    #[derive(Debug, Clone)]
+    struct Inner {
+        name: String,
+        dropped: Rc<Cell<usize>>,
+    }
+
+    impl Drop for Inner {
+        fn drop(&mut self) {
+            self.dropped.set(self.dropped.get() + 1);
+        }
+    }
+
+    #[test]
+    fn arena() {
+        let dropped = Rc::new(Cell::new(0));
+        let mut arena = TrailerArena::with_chunk_size(64);
+
+        {
+            let a = arena.alloc(
+                Inner {
+                    name: "a".to_string(),
+                    dropped: dropped.clone(),
+                },
+                4,
+            );
+            let b = arena.alloc(1u64, 100);
+            a.bytes_mut().copy_from_slice(&[1, 2, 3, 4]);
+            a.name.push('b');
+            b.bytes_mut()[99] = 1;
+            **b += 1;
+
+            assert_eq!(a.name, "ab");
+            assert_eq!(a.bytes(), &[1, 2, 3, 4]);
+            assert_eq!(**b, 2);
+            assert_eq!(b.capacity(), 100);
+            assert_eq!(&**b as *const u64 as usize % mem::align_of::<u64>(), 0);
+
+            let owned = a.to_trailer();
+            assert_eq!(owned.name, "ab");
+            assert_eq!(owned.bytes(), &[1, 2, 3, 4]);
+            drop(owned);
+            assert_eq!(dropped.get(), 1);
+        }
+        let allocated = arena.allocated_bytes();
+        assert!(allocated >= 64 + 100);
+
+        arena.reset();
+        assert_eq!(dropped.get(), 2);
+
+        for _ in 0..10 {
+            let c = arena.alloc(
+                Inner {
+                    name: "c".to_string(),
+                    dropped: dropped.clone(),
+                },
+                8,
+            );
+            assert_eq!(c.bytes(), &[0; 8]);
+        }
+        drop(arena);
+        assert_eq!(dropped.get(), 12);
+    }
+}
@@ -0,0 +1,224 @@
+use std::{
+    cmp,
+    convert::TryFrom,
+    io::{self, BufRead, Read, Seek, SeekFrom, Write},
+};
+
+use crate::{
+    allocator::{Allocator, Global},
+    Trailer,
+};
+
+/// an `io::Read`, `io::Write` and `io::Seek` adapter over the tail of a
+/// trailer
+///
+/// the cursor keeps track of the furthest byte written, so it can be stored
+/// in the header once serialization is done
+#[derive(Debug)]
+pub struct TrailerCursor<'a, T, A: Allocator = Global> {
+    trailer: &'a mut Trailer<T, u8, A>,
+    pos: u64,
+    written: usize,
+    growable: bool,
+}
+
+impl<T, A: Allocator> Trailer<T, u8, A> {
+    /// a cursor at the start of the tail, that stops writing at the end of
+    /// the tail
+    pub fn cursor(&mut self) -> TrailerCursor<'_, T, A> {
+        TrailerCursor::new(self)
+    }
+
+    /// a cursor at the start of the tail, that grows the allocation when
+    /// writing past the end of the tail
+    pub fn growing_cursor(&mut self) -> TrailerCursor<'_, T, A> {
+        TrailerCursor::growing(self)
+    }
+}
+
+impl<'a, T, A: Allocator> TrailerCursor<'a, T, A> {
+    pub fn new(trailer: &'a mut Trailer<T, u8, A>) -> TrailerCursor<'a, T, A> {
+        TrailerCursor {
+            trailer,
+            pos: 0,
+            written: 0,
+            growable: false,
+        }
+    }
+
+    pub fn growing(trailer: &'a mut Trailer<T, u8, A>) -> TrailerCursor<'a, T, A> {
+        TrailerCursor {
+            growable: true,
+            ..TrailerCursor::new(trailer)
+        }
+    }
+
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    pub fn set_position(&mut self, pos: u64) {
+        self.pos = pos;
+    }
+
+    /// offset of the end of the furthest write, from the start of the tail
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    pub fn get_ref(&self) -> &Trailer<T, u8, A> {
+        self.trailer
+    }
+
+    pub fn get_mut(&mut self) -> &mut Trailer<T, u8, A> {
+        self.trailer
+    }
+
+    pub fn into_inner(self) -> &'a mut Trailer<T, u8, A> {
+        self.trailer
+    }
+
+    /// the tail from the current position, empty if the position is past the
+    /// end
+    fn remaining(&self) -> &[u8] {
+        let bytes = self.trailer.bytes();
+        let start = cmp::min(self.pos, bytes.len() as u64) as usize;
+        &bytes[start..]
+    }
+
+    fn grow(&mut self, required: usize) -> io::Result<()> {
+        let capacity = self.trailer.capacity();
+        if required <= capacity {
+            return Ok(());
+        }
+
+        let new_capacity = cmp::max(cmp::max(capacity.saturating_mul(2), required), 8);
+        self.trailer
+            .try_reserve(new_capacity - capacity)
+            .map_err(|e| io::Error::new(io::ErrorKind::OutOfMemory, e))
+    }
+}
+
+impl<'a, T, A: Allocator> Write for TrailerCursor<'a, T, A> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let start = usize::try_from(self.pos)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "position overflow"))?;
+
+        if self.growable {
+            let required = start
+                .checked_add(buf.len())
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "position overflow"))?;
+            self.grow(required)?;
+        }
+
+        let bytes = self.trailer.bytes_mut();
+        if start >= bytes.len() {
+            return Ok(0);
+        }
+
+        let count = cmp::min(buf.len(), bytes.len() - start);
+        bytes[start..start + count].copy_from_slice(&buf[..count]);
+        self.pos += count as u64;
+        self.written = cmp::max(self.written, start + count);
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a, T, A: Allocator> Read for TrailerCursor<'a, T, A> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = self.remaining().read(buf)?;
+        self.pos += count as u64;
+        Ok(count)
+    }
+}
+
+impl<'a, T, A: Allocator> BufRead for TrailerCursor<'a, T, A> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        Ok(self.remaining())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt as u64;
+    }
+}
+
+/// `SeekFrom::End` is relative to the capacity, not to `written()`
+impl<'a, T, A: Allocator> Seek for TrailerCursor<'a, T, A> {
+    fn seek(&mut self, style: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match style {
+            SeekFrom::Start(n) => {
+                self.pos = n;
+                return Ok(n);
+            }
+            SeekFrom::End(n) => (self.trailer.capacity() as u64, n),
+            SeekFrom::Current(n) => (self.pos, n),
+        };
+
+        match base.checked_add_signed(offset) {
+            Some(pos) => {
+                self.pos = pos;
+                Ok(pos)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_read_seek() {
+        #[derive(Debug, Default)]
+        struct Inner {
+            len: usize,
+        }
+
+        let mut a = Trailer::<Inner>::new(4);
+        let mut cursor = a.cursor();
+        assert_eq!(cursor.write(&[1, 2, 3]).unwrap(), 3);
+        assert_eq!(cursor.write(&[4, 5]).unwrap(), 1);
+        assert_eq!(cursor.write(&[6]).unwrap(), 0);
+        assert!(cursor.write_all(&[6]).is_err());
+        assert_eq!(cursor.written(), 4);
+
+        cursor.seek(SeekFrom::Start(1)).unwrap();
+        let mut buf = [0; 2];
+        cursor.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [2, 3]);
+        assert_eq!(cursor.fill_buf().unwrap(), &[4]);
+        cursor.consume(1);
+        assert_eq!(cursor.read(&mut buf).unwrap(), 0);
+
+        assert_eq!(cursor.seek(SeekFrom::End(-2)).unwrap(), 2);
+        assert_eq!(cursor.seek(SeekFrom::Current(-1)).unwrap(), 1);
+        assert!(cursor.seek(SeekFrom::Current(-2)).is_err());
+        a.len = 4;
+        assert_eq!(a.bytes(), &[1, 2, 3, 4]);
+
+        let mut b = Trailer::<Inner>::new(0);
+        let mut cursor = b.growing_cursor();
+        cursor.write_all(b"hello").unwrap();
+        cursor.seek(SeekFrom::Current(2)).unwrap();
+        write!(cursor, "world").unwrap();
+        let written = cursor.written();
+        assert_eq!(written, 12);
+        b.len = written;
+        assert!(b.capacity() >= 12);
+        assert_eq!(&b.bytes()[..b.len], b"hello\0\0world");
+
+        let mut lines = String::new();
+        let mut c = Trailer::<Inner>::new(0);
+        c.growing_cursor().write_all(b"a\nb\n").unwrap();
+        c.cursor().read_line(&mut lines).unwrap();
+        assert_eq!(lines, "a\n");
+    }
+}
@@ -0,0 +1,96 @@
+use std::convert::TryInto;
+
+use crate::{allocator::Allocator, Trailer};
+
+macro_rules! endian_accessors {
+    ($($ty:ty => $read_le:ident, $read_be:ident, $write_le:ident, $write_be:ident;)*) => {
+        impl<T, A: Allocator> Trailer<T, u8, A> {
+            $(
+                #[doc = concat!(
+                    "reads a little endian `", stringify!($ty), "` at `offset`, if it fits"
+                )]
+                pub fn $read_le(&self, offset: usize) -> Option<$ty> {
+                    self.read_array(offset).map(<$ty>::from_le_bytes)
+                }
+
+                #[doc = concat!(
+                    "reads a big endian `", stringify!($ty), "` at `offset`, if it fits"
+                )]
+                pub fn $read_be(&self, offset: usize) -> Option<$ty> {
+                    self.read_array(offset).map(<$ty>::from_be_bytes)
+                }
+
+                #[doc = concat!(
+                    "writes a little endian `", stringify!($ty), "` at `offset`, if it fits"
+                )]
+                pub fn $write_le(&mut self, offset: usize, value: $ty) -> Option<()> {
+                    self.write_array(offset, value.to_le_bytes())
+                }
+
+                #[doc = concat!(
+                    "writes a big endian `", stringify!($ty), "` at `offset`, if it fits"
+                )]
+                pub fn $write_be(&mut self, offset: usize, value: $ty) -> Option<()> {
+                    self.write_array(offset, value.to_be_bytes())
+                }
+            )*
+        }
+    };
+}
+
+endian_accessors! {
+    u16 => read_u16_le, read_u16_be, write_u16_le, write_u16_be;
+    u32 => read_u32_le, read_u32_be, write_u32_le, write_u32_be;
+    u64 => read_u64_le, read_u64_be, write_u64_le, write_u64_be;
+    i16 => read_i16_le, read_i16_be, write_i16_le, write_i16_be;
+    i32 => read_i32_le, read_i32_be, write_i32_le, write_i32_be;
+    i64 => read_i64_le, read_i64_be, write_i64_le, write_i64_be;
+}
+
+impl<T, A: Allocator> Trailer<T, u8, A> {
+    /// the bytes from `offset` to `offset + len`, if they are in the tail
+    pub(crate) fn checked_range(&self, offset: usize, len: usize) -> Option<&[u8]> {
+        let end = offset.checked_add(len)?;
+        self.bytes().get(offset..end)
+    }
+
+    pub(crate) fn checked_range_mut(&mut self, offset: usize, len: usize) -> Option<&mut [u8]> {
+        let end = offset.checked_add(len)?;
+        self.bytes_mut().get_mut(offset..end)
+    }
+
+    fn read_array<const N: usize>(&self, offset: usize) -> Option<[u8; N]> {
+        self.checked_range(offset, N)
+            .map(|bytes| bytes.try_into().expect("the range is N bytes long"))
+    }
+
+    fn write_array<const N: usize>(&mut self, offset: usize, bytes: [u8; N]) -> Option<()> {
+        self.checked_range_mut(offset, N)?.copy_from_slice(&bytes);
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn endian() {
+        let mut a = Trailer::<()>::new(8);
+        a.write_u16_le(0, 0x0102).unwrap();
+        a.write_u32_be(2, 0x0304_0506).unwrap();
+        a.write_i16_be(6, -2).unwrap();
+        assert_eq!(a.bytes(), &[2, 1, 3, 4, 5, 6, 0xff, 0xfe]);
+
+        assert_eq!(a.read_u16_le(0), Some(0x0102));
+        assert_eq!(a.read_u16_be(0), Some(0x0201));
+        assert_eq!(a.read_u32_be(2), Some(0x0304_0506));
+        assert_eq!(a.read_i16_be(6), Some(-2));
+        assert_eq!(a.read_u64_le(0), Some(0xfeff_0605_0403_0102));
+
+        assert_eq!(a.read_u32_le(5), None);
+        assert_eq!(a.read_u16_le(usize::MAX), None);
+        assert_eq!(a.write_u64_be(1, 0), None);
+        assert_eq!(a.bytes(), &[2, 1, 3, 4, 5, 6, 0xff, 0xfe]);
+    }
+}
@@ -0,0 +1,169 @@
+use std::{alloc::Layout, error::Error, fmt, io};
+
+/// the error returned by the fallible `Trailer` constructors
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrailerAllocError {
+    /// the header size plus the requested capacity does not fit in a `Layout`
+    CapacityOverflow,
+    /// the allocator returned null for this layout
+    AllocError { layout: Layout },
+}
+
+impl TrailerAllocError {
+    /// reports the error the way the infallible constructors do
+    pub(crate) fn handle(self) -> ! {
+        match self {
+            TrailerAllocError::CapacityOverflow => panic!("capacity overflow"),
+            TrailerAllocError::AllocError { layout } => std::alloc::handle_alloc_error(layout),
+        }
+    }
+}
+
+impl fmt::Display for TrailerAllocError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TrailerAllocError::CapacityOverflow => write!(f, "capacity overflow"),
+            TrailerAllocError::AllocError { layout } => write!(
+                f,
+                "memory allocation of {} bytes with alignment {} failed",
+                layout.size(),
+                layout.align()
+            ),
+        }
+    }
+}
+
+impl Error for TrailerAllocError {}
+
+/// the error returned by `Trailer::read_from`
+#[derive(Debug)]
+pub enum ReadFrameError {
+    /// the reader ended before the whole header or payload was read
+    Truncated,
+    /// the payload length decoded from the header is over the limit
+    TooLong { len: usize, max: usize },
+    /// the payload could not be allocated
+    Alloc(TrailerAllocError),
+    /// any other error from the reader
+    Io(io::Error),
+}
+
+impl From<io::Error> for ReadFrameError {
+    fn from(e: io::Error) -> Self {
+        if e.kind() == io::ErrorKind::UnexpectedEof {
+            ReadFrameError::Truncated
+        } else {
+            ReadFrameError::Io(e)
+        }
+    }
+}
+
+impl From<TrailerAllocError> for ReadFrameError {
+    fn from(e: TrailerAllocError) -> Self {
+        ReadFrameError::Alloc(e)
+    }
+}
+
+impl fmt::Display for ReadFrameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReadFrameError::Truncated => write!(f, "truncated frame"),
+            ReadFrameError::TooLong { len, max } => {
+                write!(f, "payload length {} is over the limit of {}", len, max)
+            }
+            ReadFrameError::Alloc(e) => e.fmt(f),
+            ReadFrameError::Io(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error for ReadFrameError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ReadFrameError::Alloc(e) => Some(e),
+            ReadFrameError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// the error returned when casting a byte slice to a trailer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CastError {
+    /// the slice is too short to hold the header
+    TooShort { len: usize, min: usize },
+    /// the slice holds more bytes than the header plus the capacity
+    TooLong { len: usize, max: usize },
+    /// the slice is not aligned for the header
+    Misaligned { align: usize },
+}
+
+impl fmt::Display for CastError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CastError::TooShort { len, min } => {
+                write!(f, "{} bytes is shorter than the {} bytes header", len, min)
+            }
+            CastError::TooLong { len, max } => {
+                write!(f, "{} bytes is longer than the maximum of {}", len, max)
+            }
+            CastError::Misaligned { align } => {
+                write!(f, "bytes are not aligned to {}", align)
+            }
+        }
+    }
+}
+
+impl Error for CastError {}
+
+/// the error returned when a header declares a payload longer than the tail
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PayloadLenError {
+    pub len: usize,
+    pub capacity: usize,
+}
+
+impl fmt::Display for PayloadLenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "payload length {} is over the capacity of {}",
+            self.len, self.capacity
+        )
+    }
+}
+
+impl Error for PayloadLenError {}
+
+/// the error returned by the constructors and validation generated by
+/// `#[derive(Trailer)]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegionError {
+    /// a length field of the header does not match the data given for it
+    LenMismatch {
+        field: &'static str,
+        len: usize,
+        data: usize,
+    },
+    /// the regions declared by the header end past the capacity
+    OutOfBounds { end: usize, capacity: usize },
+}
+
+impl fmt::Display for RegionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RegionError::LenMismatch { field, len, data } => write!(
+                f,
+                "field `{}` is {} but {} bytes were provided",
+                field, len, data
+            ),
+            RegionError::OutOfBounds { end, capacity } => write!(
+                f,
+                "regions end at {}, past the capacity of {}",
+                end, capacity
+            ),
+        }
+    }
+}
+
+impl Error for RegionError {}
@@ -0,0 +1,366 @@
+//! trailers following the C flexible array member convention
+//!
+//! a `CTrailer<T>` is allocated with `malloc` and laid out like this C
+//! struct, with `T` being a `#[repr(C)]` type matching `hdr_t`:
+//!
+//! ```c
+//! struct msg {
+//!     hdr_t hdr;
+//!     uint8_t data[];
+//! };
+//! ```
+//!
+//! so a pointer to the header can be handed to C code, which releases it with
+//! `free`, and a block allocated by C code with
+//! `malloc(sizeof(struct msg) + capacity)` can be wrapped with `from_raw`.
+//!
+//! The `c_trailer_functions!` macro generates `extern "C"` functions for a
+//! specific header type. They pass the trailer by value as this struct:
+//!
+//! ```c
+//! struct msg_trailer {
+//!     struct msg *ptr;
+//!     size_t capacity;
+//! };
+//!
+//! struct msg_trailer msg_new(hdr_t hdr, size_t capacity);
+//! size_t msg_capacity(const struct msg_trailer *trailer);
+//! void msg_free(struct msg_trailer trailer);
+//! ```
+//!
+//! No C header is generated from Rust: the declarations above have to be
+//! written by hand, or produced by a tool like `cbindgen`.
+//!
+//! A `Trailer<T>` cannot be handed to C code directly, since its allocation
+//! starts with the capacity and comes from the Rust allocator.
+//! `CTrailer::from_trailer` and `CTrailer::into_trailer` convert between both,
+//! moving the header and copying the tail into a new allocation
+
+use std::{
+    alloc::Layout,
+    fmt,
+    marker::PhantomData,
+    mem,
+    ops::{Deref, DerefMut},
+    ptr::{self, NonNull},
+    slice,
+};
+
+use crate::{Trailer, TrailerAllocError};
+
+/// alignment guaranteed by `malloc` on the supported platforms
+const MALLOC_ALIGN: usize = 2 * mem::size_of::<usize>();
+
+struct AlignCheck<T>(PhantomData<T>);
+
+impl<T> AlignCheck<T> {
+    // evaluated when a `CTrailer<T>` is created, so an over-aligned header is
+    // a build error
+    const FITS_MALLOC: () = assert!(
+        mem::align_of::<T>() <= MALLOC_ALIGN,
+        "the header alignment is larger than what malloc guarantees"
+    );
+}
+
+/// a header of type `T` followed by `capacity()` bytes, allocated with
+/// `malloc` and released with `free`
+///
+/// it is `#[repr(C)]` so that it can be passed by value to C code as a
+/// pointer to the header and the capacity
+#[repr(C)]
+pub struct CTrailer<T> {
+    ptr: NonNull<T>,
+    capacity: usize,
+}
+
+unsafe impl<T: Send> Send for CTrailer<T> {}
+unsafe impl<T: Sync> Sync for CTrailer<T> {}
+
+impl<T> CTrailer<T> {
+    /// allocates the header followed by `capacity` zeroed bytes
+    pub fn with_header(t: T, capacity: usize) -> CTrailer<T> {
+        CTrailer::try_with_header(t, capacity).unwrap_or_else(|e| e.handle())
+    }
+
+    /// the header alignment must not be larger than what `malloc`
+    /// guarantees, which is checked at build time:
+    ///
+    /// ```compile_fail
+    /// #[repr(C, align(64))]
+    /// struct Aligned(u8);
+    ///
+    /// trailer::ffi::CTrailer::with_header(Aligned(0), 0);
+    /// ```
+    pub fn try_with_header(t: T, capacity: usize) -> Result<CTrailer<T>, TrailerAllocError> {
+        let ptr = CTrailer::<T>::allocate(capacity)?;
+        unsafe { ptr.as_ptr().write(t) };
+
+        Ok(CTrailer { ptr, capacity })
+    }
+
+    /// moves the header of `trailer` into a new `malloc` allocation, copying
+    /// the tail
+    pub fn from_trailer(trailer: Trailer<T>) -> CTrailer<T> {
+        CTrailer::try_from_trailer(trailer).unwrap_or_else(|e| e.handle())
+    }
+
+    /// like `from_trailer`, but an allocation failure is returned as an
+    /// error, after dropping `trailer`
+    pub fn try_from_trailer(trailer: Trailer<T>) -> Result<CTrailer<T>, TrailerAllocError> {
+        let ptr = CTrailer::<T>::allocate(trailer.capacity())?;
+        let (header, capacity) = trailer.into_raw();
+        unsafe {
+            let header = header.as_ptr() as *mut u8;
+            ptr::copy_nonoverlapping(header as *const T, ptr.as_ptr(), 1);
+            ptr::copy_nonoverlapping(
+                header.add(Trailer::<T>::tail_offset()),
+                (ptr.as_ptr() as *mut u8).add(mem::size_of::<T>()),
+                capacity,
+            );
+            // the header was moved, only the memory is released
+            Trailer::<T>::deallocate(header, capacity);
+        }
+
+        Ok(CTrailer { ptr, capacity })
+    }
+
+    /// moves the header into a new `Trailer`, copying the tail, and frees
+    /// the `malloc` allocation
+    pub fn into_trailer(self) -> Trailer<T> {
+        let this = mem::ManuallyDrop::new(self);
+        unsafe {
+            let trailer =
+                Trailer::<T>::try_allocate_uninit(this.capacity).unwrap_or_else(|e| e.handle());
+            ptr::copy_nonoverlapping(this.ptr.as_ptr(), trailer.ptr.as_ptr() as *mut T, 1);
+            ptr::copy_nonoverlapping(this.tail_ptr(), trailer.tail_ptr(), this.capacity);
+            // the header was moved, only the memory is released
+            libc::free(this.ptr.as_ptr() as *mut libc::c_void);
+
+            trailer
+        }
+    }
+
+    /// allocates a zeroed block for the header and `capacity` bytes
+    fn allocate(capacity: usize) -> Result<NonNull<T>, TrailerAllocError> {
+        #[allow(clippy::let_unit_value)]
+        let () = AlignCheck::<T>::FITS_MALLOC;
+
+        // the flexible array member starts right after the header, since the
+        // size of a C struct is a multiple of its alignment
+        let size = mem::size_of::<T>()
+            .checked_add(capacity)
+            .ok_or(TrailerAllocError::CapacityOverflow)?;
+        let layout = Layout::from_size_align(size, mem::align_of::<T>())
+            .map_err(|_| TrailerAllocError::CapacityOverflow)?;
+
+        // `malloc(0)` can return null, so at least one byte is requested
+        let ptr = unsafe { libc::calloc(1, size.max(1)) } as *mut T;
+        NonNull::new(ptr).ok_or(TrailerAllocError::AllocError { layout })
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.tail_ptr(), self.capacity) }
+    }
+
+    pub fn bytes_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.tail_ptr(), self.capacity) }
+    }
+
+    /// consumes the trailer, returning a pointer to the header and the
+    /// capacity
+    ///
+    /// the pointer can be released with `free`, which does not drop the
+    /// header
+    pub fn into_raw(self) -> (NonNull<T>, usize) {
+        let this = mem::ManuallyDrop::new(self);
+        (this.ptr, this.capacity)
+    }
+
+    /// wraps a block allocated with `malloc`
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been allocated with `malloc`, with a size of at least
+    /// `size_of::<T>() + capacity` bytes, and must point to an initialized
+    /// header followed by `capacity` initialized bytes
+    pub unsafe fn from_raw(ptr: NonNull<T>, capacity: usize) -> CTrailer<T> {
+        #[allow(clippy::let_unit_value)]
+        let () = AlignCheck::<T>::FITS_MALLOC;
+        CTrailer { ptr, capacity }
+    }
+
+    fn tail_ptr(&self) -> *mut u8 {
+        unsafe { (self.ptr.as_ptr() as *mut u8).add(mem::size_of::<T>()) }
+    }
+}
+
+impl<T> From<Trailer<T>> for CTrailer<T> {
+    fn from(trailer: Trailer<T>) -> Self {
+        CTrailer::from_trailer(trailer)
+    }
+}
+
+impl<T> From<CTrailer<T>> for Trailer<T> {
+    fn from(trailer: CTrailer<T>) -> Self {
+        trailer.into_trailer()
+    }
+}
+
+impl<T> Drop for CTrailer<T> {
+    fn drop(&mut self) {
+        unsafe {
+            ptr::drop_in_place(self.ptr.as_ptr());
+            libc::free(self.ptr.as_ptr() as *mut libc::c_void);
+        }
+    }
+}
+
+impl<T> Deref for CTrailer<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T> DerefMut for CTrailer<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for CTrailer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CTrailer")
+            .field("header", &**self)
+            .field("bytes", &self.bytes())
+            .finish()
+    }
+}
+
+/// generates `extern "C"` functions to create, get the capacity of and free a
+/// `CTrailer` of a specific header type
+///
+/// ```
+/// #[repr(C)]
+/// pub struct Header {
+///     len: u32,
+/// }
+///
+/// trailer::c_trailer_functions!(Header, new = msg_new, capacity = msg_capacity, free = msg_free);
+///
+/// let trailer = msg_new(Header { len: 0 }, 16);
+/// assert_eq!(unsafe { msg_capacity(&trailer) }, 16);
+/// unsafe { msg_free(trailer) };
+/// ```
+#[macro_export]
+macro_rules! c_trailer_functions {
+    ($header:ty, new = $new:ident, capacity = $capacity:ident, free = $free:ident) => {
+        /// allocates a trailer with `capacity` zeroed bytes, aborting if the
+        /// allocation fails
+        #[no_mangle]
+        pub extern "C" fn $new(header: $header, capacity: usize) -> $crate::ffi::CTrailer<$header> {
+            $crate::ffi::CTrailer::with_header(header, capacity)
+        }
+
+        /// # Safety
+        ///
+        /// `trailer` must point to a valid trailer
+        #[no_mangle]
+        pub unsafe extern "C" fn $capacity(
+            trailer: *const $crate::ffi::CTrailer<$header>,
+        ) -> usize {
+            (*trailer).capacity()
+        }
+
+        /// # Safety
+        ///
+        /// `trailer` must come from the creation function and not have been
+        /// freed already
+        #[no_mangle]
+        pub unsafe extern "C" fn $free(trailer: $crate::ffi::CTrailer<$header>) {
+            drop(trailer)
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[repr(C)]
+    #[derive(Debug)]
+    pub struct Header {
+        len: u32,
+        kind: u16,
+    }
+
+    #[test]
+    fn c_layout() {
+        let mut a = CTrailer::with_header(Header { len: 0, kind: 1 }, 4);
+        a.bytes_mut().copy_from_slice(&[1, 2, 3, 4]);
+        a.len = 4;
+        assert_eq!(a.bytes(), &[1, 2, 3, 4]);
+        assert_eq!(a.tail_ptr() as usize - a.ptr.as_ptr() as usize, 8);
+
+        // released by C code
+        let (ptr, capacity) = a.into_raw();
+        assert_eq!(capacity, 4);
+        unsafe { libc::free(ptr.as_ptr() as *mut libc::c_void) };
+    }
+
+    #[test]
+    fn from_c() {
+        unsafe {
+            let size = mem::size_of::<Header>() + 3;
+            let ptr = libc::malloc(size) as *mut Header;
+            ptr.write(Header { len: 3, kind: 2 });
+            ptr::copy_nonoverlapping([7u8, 8, 9].as_ptr(), (ptr as *mut u8).add(8), 3);
+
+            let a = CTrailer::from_raw(NonNull::new(ptr).unwrap(), 3);
+            assert_eq!(a.len, 3);
+            assert_eq!(a.kind, 2);
+            assert_eq!(a.bytes(), &[7, 8, 9]);
+        }
+    }
+
+    #[test]
+    fn trailer_conversions() {
+        let mut a = Trailer::with_header(Header { len: 2, kind: 1 }, 3);
+        a.bytes_mut().copy_from_slice(&[1, 2, 3]);
+
+        let c = CTrailer::from_trailer(a);
+        assert_eq!(c.len, 2);
+        assert_eq!(c.bytes(), &[1, 2, 3]);
+
+        let b: Trailer<Header> = c.into();
+        assert_eq!(b.kind, 1);
+        assert_eq!(b.capacity(), 3);
+        assert_eq!(b.bytes(), &[1, 2, 3]);
+
+        let c: CTrailer<Header> = b.into();
+        assert_eq!(c.into_trailer().bytes(), &[1, 2, 3]);
+
+        let s = CTrailer::from_trailer(Trailer::with_header(String::from("owned"), 0));
+        assert_eq!(&*s, "owned");
+        assert_eq!(&*s.into_trailer(), "owned");
+    }
+
+    c_trailer_functions!(
+        Header,
+        new = test_new,
+        capacity = test_capacity,
+        free = test_free
+    );
+
+    #[test]
+    fn extern_functions() {
+        let a = test_new(Header { len: 0, kind: 3 }, 10);
+        assert_eq!(unsafe { test_capacity(&a) }, 10);
+        assert_eq!(a.bytes(), &[0; 10]);
+        unsafe { test_free(a) };
+    }
+}
@@ -0,0 +1,152 @@
+use std::{
+    cmp,
+    ops::{Deref, DerefMut},
+};
+
+use crate::{error::PayloadLenError, Trailer};
+
+/// headers that declare how many bytes of the tail hold the payload
+pub trait TrailerHeader {
+    fn payload_len(&self) -> usize;
+}
+
+/// a `Trailer` whose header declares the length of its payload
+///
+/// `payload()` only returns that prefix of the tail, while the full tail is
+/// still available through `bytes()`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PayloadTrailer<T: TrailerHeader> {
+    trailer: Trailer<T>,
+}
+
+impl<T: TrailerHeader> PayloadTrailer<T> {
+    pub fn new(trailer: Trailer<T>) -> PayloadTrailer<T> {
+        PayloadTrailer { trailer }
+    }
+
+    /// wraps `trailer` if its header length fits in the tail
+    pub fn try_new(trailer: Trailer<T>) -> Result<PayloadTrailer<T>, PayloadLenError> {
+        check(&trailer)?;
+        Ok(PayloadTrailer { trailer })
+    }
+
+    pub fn into_inner(self) -> Trailer<T> {
+        self.trailer
+    }
+
+    /// the first `payload_len()` bytes of the tail
+    ///
+    /// a header length over `capacity()` fails a debug assertion, and is
+    /// clamped to the capacity in release builds
+    pub fn payload(&self) -> &[u8] {
+        let len = self.clamped_len();
+        &self.trailer.bytes()[..len]
+    }
+
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        let len = self.clamped_len();
+        &mut self.trailer.bytes_mut()[..len]
+    }
+
+    /// like `payload()`, but rejects a header length over `capacity()`
+    pub fn try_payload(&self) -> Result<&[u8], PayloadLenError> {
+        let len = check(&self.trailer)?;
+        Ok(&self.trailer.bytes()[..len])
+    }
+
+    pub fn try_payload_mut(&mut self) -> Result<&mut [u8], PayloadLenError> {
+        let len = check(&self.trailer)?;
+        Ok(&mut self.trailer.bytes_mut()[..len])
+    }
+
+    fn clamped_len(&self) -> usize {
+        let len = self.trailer.payload_len();
+        debug_assert!(
+            len <= self.trailer.capacity(),
+            "payload length {} is over the capacity of {}",
+            len,
+            self.trailer.capacity()
+        );
+        cmp::min(len, self.trailer.capacity())
+    }
+}
+
+fn check<T: TrailerHeader>(trailer: &Trailer<T>) -> Result<usize, PayloadLenError> {
+    let len = trailer.payload_len();
+    if len > trailer.capacity() {
+        Err(PayloadLenError {
+            len,
+            capacity: trailer.capacity(),
+        })
+    } else {
+        Ok(len)
+    }
+}
+
+impl<T: TrailerHeader> From<Trailer<T>> for PayloadTrailer<T> {
+    fn from(trailer: Trailer<T>) -> Self {
+        PayloadTrailer::new(trailer)
+    }
+}
+
+impl<T: TrailerHeader> Deref for PayloadTrailer<T> {
+    type Target = Trailer<T>;
+    fn deref(&self) -> &Trailer<T> {
+        &self.trailer
+    }
+}
+
+impl<T: TrailerHeader> DerefMut for PayloadTrailer<T> {
+    fn deref_mut(&mut self) -> &mut Trailer<T> {
+        &mut self.trailer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct Inner {
+        payload_len: usize,
+    }
+
+    impl TrailerHeader for Inner {
+        fn payload_len(&self) -> usize {
+            self.payload_len
+        }
+    }
+
+    #[test]
+    fn payload() {
+        let mut a = PayloadTrailer::new(Trailer::<Inner>::new(4));
+        assert_eq!(a.payload(), &[] as &[u8]);
+
+        a.bytes_mut().copy_from_slice(&[1, 2, 3, 4]);
+        a.payload_len = 2;
+        assert_eq!(a.payload(), &[1, 2]);
+        a.payload_mut()[1] = 5;
+        assert_eq!(a.try_payload().unwrap(), &[1, 5]);
+        assert_eq!(a.bytes(), &[1, 5, 3, 4]);
+
+        a.payload_len = 5;
+        assert_eq!(
+            a.try_payload().unwrap_err(),
+            PayloadLenError {
+                len: 5,
+                capacity: 4
+            }
+        );
+        assert!(a.try_payload_mut().is_err());
+        assert!(PayloadTrailer::try_new(a.into_inner()).is_err());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "payload length 5 is over the capacity of 4")]
+    fn payload_too_long() {
+        let mut a = PayloadTrailer::new(Trailer::<Inner>::new(4));
+        a.payload_len = 5;
+        a.payload();
+    }
+}
@@ -1,98 +1,645 @@
 use std::{
     alloc,
+    cmp::Ordering,
+    convert::Infallible,
     default::Default,
+    hash::{Hash, Hasher},
     marker::PhantomData,
     mem,
     ops::{Deref, DerefMut, Drop},
-    ptr, slice,
+    ptr::{self, NonNull},
+    slice,
 };
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct Trailer<T> {
-    ptr: *mut u8,
-    size: usize,
-    phantom: PhantomData<T>,
+mod allocator;
+mod arena;
+mod cursor;
+mod endian;
+mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod header;
+mod multi;
+mod pod;
+mod pool;
+pub mod rc;
+mod read;
+#[cfg(feature = "serde")]
+pub mod serde;
+pub mod sync;
+mod thin;
+mod uninit;
+mod vec;
+mod view;
+
+use allocator::{Allocator, Global};
+
+pub use arena::{ArenaTrailer, TrailerArena};
+pub use cursor::TrailerCursor;
+pub use error::{CastError, PayloadLenError, ReadFrameError, RegionError, TrailerAllocError};
+pub use header::{PayloadTrailer, TrailerHeader};
+pub use multi::MultiTrailer;
+#[doc(hidden)]
+pub use pod::__private;
+pub use pod::PlainData;
+pub use pool::{PoolStats, PooledTrailer, TrailerPool};
+pub use rc::RcTrailer;
+pub use sync::ArcTrailer;
+pub use thin::ThinTrailer;
+#[cfg(feature = "derive")]
+pub use trailer_derive::Trailer;
+pub use uninit::{UninitBytes, UninitTrailer};
+pub use vec::TrailerVec;
+pub use view::{TrailerMut, TrailerRef};
+
+/// a header of type `T` followed by `capacity()` elements of type `U` in the
+/// same allocation
+///
+/// the memory comes from the allocator `A`, which can be chosen with the
+/// `allocator-api2` feature. It is stored next to the pointer, so a zero sized
+/// allocator like `Global` takes no space
+#[derive(Debug)]
+pub struct Trailer<T, U = u8, A: Allocator = Global> {
+    // points to the header, never null
+    ptr: NonNull<u8>,
+    capacity: usize,
+    // the trailer owns a `T` and the `U` elements: this makes it covariant
+    // over both, and tells the drop checker that they are dropped with it
+    phantom: PhantomData<(T, U)>,
+    alloc: A,
 }
 
 impl<T: Default> Trailer<T> {
     pub fn new(capacity: usize) -> Trailer<T> {
+        Trailer::new_in(capacity, Global)
+    }
+
+    pub fn try_new(capacity: usize) -> Result<Trailer<T>, TrailerAllocError> {
+        // built before the allocation, so that a panicking `default()` does
+        // not drop an uninitialized header
+        let t = T::default();
         unsafe {
-            let trailer = Trailer::allocate(capacity);
-            let ptr = trailer.ptr as *mut T;
-            ptr.write(T::default());
-            trailer
+            let trailer = Trailer::try_allocate(capacity)?;
+            let ptr = trailer.ptr.as_ptr() as *mut T;
+            ptr.write(t);
+            Ok(trailer)
         }
     }
 }
 
+impl<T: Default, A: Allocator> Trailer<T, u8, A> {
+    pub fn new_in(capacity: usize, alloc: A) -> Trailer<T, u8, A> {
+        // the header is built first, a panicking `default()` then has nothing
+        // to clean up
+        Trailer::with_header_in(T::default(), capacity, alloc)
+    }
+}
+
 impl<T: Copy> Trailer<T> {
     pub fn from(t: T, capacity: usize) -> Trailer<T> {
+        Trailer::from_in(t, capacity, Global)
+    }
+
+    pub fn try_from(t: T, capacity: usize) -> Result<Trailer<T>, TrailerAllocError> {
         unsafe {
-            let trailer = Trailer::allocate(capacity);
-            let ptr = trailer.ptr as *mut T;
+            let trailer = Trailer::try_allocate(capacity)?;
+            let ptr = trailer.ptr.as_ptr() as *mut T;
             ptr.write(t);
 
-            trailer
+            Ok(trailer)
         }
     }
 }
 
+impl<T: Copy, A: Allocator> Trailer<T, u8, A> {
+    pub fn from_in(t: T, capacity: usize, alloc: A) -> Trailer<T, u8, A> {
+        Trailer::with_header_in(t, capacity, alloc)
+    }
+}
+
 impl<T> Trailer<T> {
-    unsafe fn allocate(capacity: usize) -> Trailer<T> {
-        let size = mem::size_of::<T>() + capacity;
-        let align = mem::align_of::<T>();
-        let layout = alloc::Layout::from_size_align(size, align).unwrap();
-        let ptr = alloc::alloc_zeroed(layout);
+    /// creates a trailer from any owned header, with a zeroed tail
+    pub fn with_header(t: T, capacity: usize) -> Trailer<T> {
+        Trailer::with_header_in(t, capacity, Global)
+    }
+
+    /// fills the zeroed tail first, then builds the header from it
+    pub fn new_with<F: FnOnce(&mut [u8]) -> T>(capacity: usize, f: F) -> Trailer<T> {
+        match Trailer::try_new_with(capacity, |bytes| Ok::<T, Infallible>(f(bytes))) {
+            Ok(trailer) => trailer,
+            Err(e) => match e {},
+        }
+    }
+
+    /// like `new_with`, but the header construction can fail, in which case
+    /// the allocation is freed and the error returned
+    pub fn try_new_with<E, F: FnOnce(&mut [u8]) -> Result<T, E>>(
+        capacity: usize,
+        f: F,
+    ) -> Result<Trailer<T>, E> {
+        unsafe {
+            let trailer = mem::ManuallyDrop::new(Trailer::<T>::allocate(capacity));
+            // the header is not written yet, so only the memory is released
+            // if `f` fails or panics
+            let guard = DeallocOnDrop::<T, u8> {
+                ptr: trailer.ptr.as_ptr(),
+                capacity,
+                phantom: PhantomData,
+            };
+
+            let t = f(slice::from_raw_parts_mut(trailer.tail_ptr(), capacity))?;
+            mem::forget(guard);
+            (trailer.ptr.as_ptr() as *mut T).write(t);
 
+            Ok(mem::ManuallyDrop::into_inner(trailer))
+        }
+    }
+}
+
+impl<T, A: Allocator> Trailer<T, u8, A> {
+    pub fn with_header_in(t: T, capacity: usize, alloc: A) -> Trailer<T, u8, A> {
+        unsafe {
+            let trailer = Trailer::allocate_in(capacity, alloc);
+            let ptr = trailer.ptr.as_ptr() as *mut T;
+            ptr.write(t);
+
+            trailer
+        }
+    }
+}
+
+impl<T, U: Clone> Trailer<T, U> {
+    /// creates a trailer whose tail holds a clone of `elements`
+    pub fn from_slice(t: T, elements: &[U]) -> Trailer<T, U> {
+        Trailer::from_fn(t, elements.len(), |i| elements[i].clone())
+    }
+}
+
+impl<T, U> Trailer<T, U> {
+    /// creates a trailer with `capacity` elements, each one produced by
+    /// calling `f` with its index
+    pub fn from_fn<F: FnMut(usize) -> U>(t: T, capacity: usize, f: F) -> Trailer<T, U> {
+        Trailer::from_fn_in(t, capacity, f, Global)
+    }
+
+    /// layout of the allocation holding the capacity, a `T`, then `capacity`
+    /// elements of type `U`, along with the offsets of the header and of the
+    /// first element
+    ///
+    /// the capacity is stored in front of the header so that `ThinTrailer`
+    /// can share the allocation
+    fn layout(capacity: usize) -> Result<(alloc::Layout, usize, usize), TrailerAllocError> {
+        let array =
+            alloc::Layout::array::<U>(capacity).map_err(|_| TrailerAllocError::CapacityOverflow)?;
+        let (layout, header_offset) = alloc::Layout::new::<usize>()
+            .extend(alloc::Layout::new::<T>())
+            .map_err(|_| TrailerAllocError::CapacityOverflow)?;
+        let (layout, tail_offset) = layout
+            .extend(array)
+            .map_err(|_| TrailerAllocError::CapacityOverflow)?;
+        Ok((layout, header_offset, tail_offset))
+    }
+
+    fn header_offset() -> usize {
+        Trailer::<T, U>::layout(0)
+            .expect("the header layout is always valid")
+            .1
+    }
+
+    /// offset of the first element from the header
+    fn tail_offset() -> usize {
+        let (_, header_offset, tail_offset) =
+            Trailer::<T, U>::layout(0).expect("the header layout is always valid");
+        tail_offset - header_offset
+    }
+
+    /// reads the capacity stored in front of the header
+    unsafe fn stored_capacity(ptr: *const u8) -> usize {
+        *(ptr.sub(Trailer::<T, U>::header_offset()) as *const usize)
+    }
+
+    /// frees an allocation from a pointer to its header, without dropping
+    /// anything
+    unsafe fn deallocate(ptr: *mut u8, capacity: usize) {
+        Trailer::<T, U>::deallocate_in(ptr, capacity, &Global)
+    }
+
+    unsafe fn allocate(capacity: usize) -> Trailer<T, U> {
+        Trailer::allocate_in(capacity, Global)
+    }
+
+    unsafe fn try_allocate(capacity: usize) -> Result<Trailer<T, U>, TrailerAllocError> {
+        Trailer::try_allocate_in(capacity, Global, true)
+    }
+
+    /// like `try_allocate`, but the memory is left uninitialized
+    unsafe fn try_allocate_uninit(capacity: usize) -> Result<Trailer<T, U>, TrailerAllocError> {
+        Trailer::try_allocate_in(capacity, Global, false)
+    }
+
+    /// consumes the trailer, returning a pointer to the header and the
+    /// capacity
+    ///
+    /// the header is inside an allocation laid out like `allocate` does it:
+    /// the capacity as a `usize`, then the header, then `capacity` elements,
+    /// each of them at the offset computed by `Layout::extend`. Nothing is
+    /// dropped or freed until the trailer is rebuilt with `from_raw`
+    pub fn into_raw(self) -> (NonNull<T>, usize) {
+        let this = mem::ManuallyDrop::new(self);
+        (this.ptr.cast(), this.capacity)
+    }
+
+    /// rebuilds a trailer from the result of `into_raw`
+    ///
+    /// # Safety
+    ///
+    /// `ptr` and `capacity` must come from a call to `into_raw` on a
+    /// `Trailer<T, U>`, and the trailer must not have been rebuilt already
+    pub unsafe fn from_raw(ptr: NonNull<T>, capacity: usize) -> Trailer<T, U> {
+        debug_assert_eq!(Trailer::<T, U>::stored_capacity(ptr.as_ptr() as *const u8), capacity);
         Trailer {
-            ptr,
-            size,
+            ptr: ptr.cast(),
+            capacity,
             phantom: PhantomData,
+            alloc: Global,
         }
     }
 
-    pub fn bytes(&self) -> &[u8] {
+    /// consumes the trailer without freeing it, returning references to the
+    /// header and the tail that live as long as needed
+    pub fn leak<'a>(self) -> (&'a mut T, &'a mut [U])
+    where
+        T: 'a,
+        U: 'a,
+    {
+        let (ptr, capacity) = self.into_raw();
         unsafe {
-            slice::from_raw_parts(
-                self.ptr.add(mem::size_of::<T>()),
-                self.size - mem::size_of::<T>(),
+            let tail = ptr.as_ptr().cast::<u8>().add(Trailer::<T, U>::tail_offset());
+            (
+                &mut *ptr.as_ptr(),
+                slice::from_raw_parts_mut(tail as *mut U, capacity),
             )
         }
     }
+}
+
+impl<T, U, A: Allocator> Trailer<T, U, A> {
+    /// like `from_fn`, with the memory coming from `alloc`
+    pub fn from_fn_in<F: FnMut(usize) -> U>(
+        t: T,
+        capacity: usize,
+        mut f: F,
+        alloc: A,
+    ) -> Trailer<T, U, A> {
+        // frees the allocation and drops what was written if `f` panics
+        struct Guard<'a, T, U, A: Allocator> {
+            ptr: *mut u8,
+            capacity: usize,
+            initialized: usize,
+            alloc: &'a A,
+            phantom: PhantomData<(T, U)>,
+        }
+
+        impl<'a, T, U, A: Allocator> Drop for Guard<'a, T, U, A> {
+            fn drop(&mut self) {
+                unsafe {
+                    ptr::drop_in_place(self.ptr as *mut T);
+                    ptr::drop_in_place(ptr::slice_from_raw_parts_mut(
+                        self.ptr.add(Trailer::<T, U>::tail_offset()) as *mut U,
+                        self.initialized,
+                    ));
+                    Trailer::<T, U, A>::deallocate_in(self.ptr, self.capacity, self.alloc);
+                }
+            }
+        }
 
-    pub fn bytes_mut(&mut self) -> &mut [u8] {
         unsafe {
-            ::std::slice::from_raw_parts_mut(
-                self.ptr.add(mem::size_of::<T>()),
-                self.size - mem::size_of::<T>(),
+            let trailer = mem::ManuallyDrop::new(Trailer::<T, U, A>::allocate_in(capacity, alloc));
+            (trailer.ptr.as_ptr() as *mut T).write(t);
+
+            let mut guard = Guard::<T, U, A> {
+                ptr: trailer.ptr.as_ptr(),
+                capacity,
+                initialized: 0,
+                alloc: &trailer.alloc,
+                phantom: PhantomData,
+            };
+            let elements = trailer.tail_ptr();
+            while guard.initialized < capacity {
+                elements.add(guard.initialized).write(f(guard.initialized));
+                guard.initialized += 1;
+            }
+            mem::forget(guard);
+
+            mem::ManuallyDrop::into_inner(trailer)
+        }
+    }
+
+    /// layout of the current allocation, which was checked when allocating
+    fn current_layout(&self) -> alloc::Layout {
+        Trailer::<T, U>::layout(self.capacity)
+            .expect("the layout was checked at allocation")
+            .0
+    }
+
+    unsafe fn deallocate_in(ptr: *mut u8, capacity: usize, alloc: &A) {
+        let (layout, header_offset, _) =
+            Trailer::<T, U>::layout(capacity).expect("the layout was checked at allocation");
+        alloc.deallocate(NonNull::new_unchecked(ptr.sub(header_offset)), layout);
+    }
+
+    unsafe fn allocate_in(capacity: usize, alloc: A) -> Trailer<T, U, A> {
+        Trailer::try_allocate_in(capacity, alloc, true).unwrap_or_else(|e| e.handle())
+    }
+
+    unsafe fn try_allocate_in(
+        capacity: usize,
+        alloc: A,
+        zeroed: bool,
+    ) -> Result<Trailer<T, U, A>, TrailerAllocError> {
+        let (layout, header_offset, _) = Trailer::<T, U>::layout(capacity)?;
+        let block = if zeroed {
+            alloc.allocate_zeroed(layout)
+        } else {
+            alloc.allocate(layout)
+        };
+        let base = block
+            .map_err(|_| TrailerAllocError::AllocError { layout })?
+            .cast::<u8>();
+        (base.as_ptr() as *mut usize).write(capacity);
+
+        Ok(Trailer {
+            ptr: NonNull::new_unchecked(base.as_ptr().add(header_offset)),
+            capacity,
+            phantom: PhantomData,
+            alloc,
+        })
+    }
+
+    /// size of the header and the tail
+    #[cfg(test)]
+    fn size(&self) -> usize {
+        self.current_layout().size() - Trailer::<T, U>::header_offset()
+    }
+
+    fn tail_ptr(&self) -> *mut U {
+        unsafe { self.ptr.as_ptr().add(Trailer::<T, U>::tail_offset()) as *mut U }
+    }
+
+    pub fn as_slice(&self) -> &[U] {
+        unsafe { slice::from_raw_parts(self.tail_ptr(), self.capacity) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [U] {
+        unsafe { slice::from_raw_parts_mut(self.tail_ptr(), self.capacity) }
+    }
+
+    /// the header and the tail, borrowed at the same time
+    pub fn parts(&self) -> (&T, &[U]) {
+        (self, self.as_slice())
+    }
+
+    /// the header and the tail, mutably borrowed at the same time
+    pub fn parts_mut(&mut self) -> (&mut T, &mut [U]) {
+        unsafe {
+            (
+                &mut *(self.ptr.as_ptr() as *mut T),
+                slice::from_raw_parts_mut(self.tail_ptr(), self.capacity),
             )
         }
     }
 
+    pub fn view(&self) -> TrailerRef<'_, T, U> {
+        TrailerRef::new(self, self.as_slice())
+    }
+
+    pub fn view_mut(&mut self) -> TrailerMut<'_, T, U> {
+        let (header, tail) = self.parts_mut();
+        TrailerMut::new(header, tail)
+    }
+
+    /// number of `U` elements in the tail
     pub fn capacity(&self) -> usize {
-      self.size - mem::size_of::<T>()
+        self.capacity
+    }
+
+    /// the allocator the trailer was created with
+    pub fn allocator(&self) -> &A {
+        &self.alloc
+    }
+
+    pub fn as_ptr(&self) -> *const T {
+        self.ptr.as_ptr() as *const T
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        self.ptr.as_ptr() as *mut T
+    }
+
+    /// pointer to the first element of the tail
+    pub fn as_tail_ptr(&self) -> *const U {
+        self.tail_ptr()
+    }
+
+    pub fn as_mut_tail_ptr(&mut self) -> *mut U {
+        self.tail_ptr()
+    }
+
+    unsafe fn realloc(&mut self, new_capacity: usize) {
+        self.try_realloc(new_capacity).unwrap_or_else(|e| e.handle())
+    }
+
+    /// changes the capacity without initializing new elements or dropping
+    /// removed ones
+    unsafe fn try_realloc(&mut self, new_capacity: usize) -> Result<(), TrailerAllocError> {
+        if new_capacity == self.capacity {
+            return Ok(());
+        }
+
+        let old_layout = self.current_layout();
+        let (new_layout, header_offset, _) = Trailer::<T, U>::layout(new_capacity)?;
+        let base = NonNull::new_unchecked(self.ptr.as_ptr().sub(header_offset));
+        let block = if new_layout.size() > old_layout.size() {
+            self.alloc.grow(base, old_layout, new_layout)
+        } else {
+            self.alloc.shrink(base, old_layout, new_layout)
+        };
+        let base = block
+            .map_err(|_| TrailerAllocError::AllocError { layout: new_layout })?
+            .cast::<u8>();
+        (base.as_ptr() as *mut usize).write(new_capacity);
+
+        self.ptr = NonNull::new_unchecked(base.as_ptr().add(header_offset));
+        self.capacity = new_capacity;
+        Ok(())
     }
 }
 
-impl<T> Drop for Trailer<T> {
+impl<T, A: Allocator> Trailer<T, u8, A> {
+    pub fn bytes(&self) -> &[u8] {
+        self.as_slice()
+    }
+
+    pub fn bytes_mut(&mut self) -> &mut [u8] {
+        self.as_mut_slice()
+    }
+
+    /// grows or shrinks the trailing buffer in place, zero-filling new bytes
+    ///
+    /// the header stays where it is in the allocation, but the allocation
+    /// itself may move
+    pub fn resize(&mut self, new_capacity: usize) {
+        let old_capacity = self.capacity();
+        unsafe {
+            self.resize_uninit(new_capacity);
+            if new_capacity > old_capacity {
+                ptr::write_bytes(
+                    self.tail_ptr().add(old_capacity),
+                    0,
+                    new_capacity - old_capacity,
+                );
+            }
+        }
+    }
+
+    /// grows or shrinks the trailing buffer, leaving new bytes uninitialized
+    ///
+    /// # Safety
+    ///
+    /// the new bytes must be written before they are read through `bytes()`
+    /// or `bytes_mut()`
+    pub unsafe fn resize_uninit(&mut self, new_capacity: usize) {
+        self.realloc(new_capacity);
+    }
+
+    /// grows the trailing buffer by `additional` zeroed bytes
+    pub fn reserve(&mut self, additional: usize) {
+        self.try_reserve(additional).unwrap_or_else(|e| e.handle())
+    }
+
+    /// grows the trailing buffer by `additional` zeroed bytes, leaving the
+    /// trailer unchanged if that fails
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TrailerAllocError> {
+        let old_capacity = self.capacity();
+        let new_capacity = old_capacity
+            .checked_add(additional)
+            .ok_or(TrailerAllocError::CapacityOverflow)?;
+        unsafe {
+            self.try_realloc(new_capacity)?;
+            ptr::write_bytes(self.tail_ptr().add(old_capacity), 0, additional);
+        }
+        Ok(())
+    }
+
+    /// shrinks the trailing buffer to `min_capacity` bytes if it is larger
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        if min_capacity < self.capacity() {
+            self.resize(min_capacity);
+        }
+    }
+}
+
+impl<T, U, A: Allocator> Drop for Trailer<T, U, A> {
     fn drop(&mut self) {
-        unsafe { ptr::drop_in_place(self.ptr as *mut T) };
-        let align = mem::align_of::<T>();
-        let layout = alloc::Layout::from_size_align(self.size, align).unwrap();
-        unsafe { alloc::dealloc(self.ptr, layout) };
+        unsafe {
+            ptr::drop_in_place(self.ptr.as_ptr() as *mut T);
+            ptr::drop_in_place(self.as_mut_slice() as *mut [U]);
+        }
+        unsafe { Trailer::<T, U, A>::deallocate_in(self.ptr.as_ptr(), self.capacity, &self.alloc) };
     }
 }
 
-impl<T> Deref for Trailer<T> {
+struct DeallocOnDrop<T, U> {
+    ptr: *mut u8,
+    capacity: usize,
+    phantom: PhantomData<(T, U)>,
+}
+
+impl<T, U> Drop for DeallocOnDrop<T, U> {
+    fn drop(&mut self) {
+        unsafe { Trailer::<T, U>::deallocate(self.ptr, self.capacity) };
+    }
+}
+
+/// the trailer owns its header and elements, so it can be sent to another
+/// thread if they can
+///
+/// ```compile_fail
+/// fn is_send<T: Send>(_: T) {}
+/// is_send(trailer::Trailer::<std::rc::Rc<()>>::new(4));
+/// ```
+///
+/// ```compile_fail
+/// fn is_send<T: Send>(_: T) {}
+/// is_send(trailer::Trailer::<(), std::rc::Rc<()>>::from_slice((), &[]));
+/// ```
+unsafe impl<T: Send, U: Send, A: Allocator + Send> Send for Trailer<T, U, A> {}
+
+/// shared references to the trailer only give shared references to the
+/// header and elements
+///
+/// ```compile_fail
+/// fn is_sync<T: Sync>(_: T) {}
+/// is_sync(trailer::Trailer::<std::cell::Cell<u8>>::new(4));
+/// ```
+unsafe impl<T: Sync, U: Sync, A: Allocator + Sync> Sync for Trailer<T, U, A> {}
+
+impl<T, U, A: Allocator> Deref for Trailer<T, U, A> {
     type Target = T;
     fn deref(&self) -> &T {
-        unsafe { &*(self.ptr as *const T) }
+        unsafe { &*(self.ptr.as_ptr() as *const T) }
     }
 }
 
-impl<T> DerefMut for Trailer<T> {
+impl<T, U, A: Allocator> DerefMut for Trailer<T, U, A> {
     fn deref_mut(&mut self) -> &mut T {
-        unsafe { &mut *(self.ptr as *mut T) }
+        unsafe { &mut *(self.ptr.as_ptr() as *mut T) }
+    }
+}
+
+/// allocates a new buffer holding clones of the header and the tail
+impl<T: Clone, U: Clone, A: Allocator + Clone> Clone for Trailer<T, U, A> {
+    fn clone(&self) -> Self {
+        let elements = self.as_slice();
+        Trailer::from_fn_in(
+            (**self).clone(),
+            elements.len(),
+            |i| elements[i].clone(),
+            self.alloc.clone(),
+        )
+    }
+}
+
+/// trailers are compared by header first, then by tail contents
+impl<T: PartialEq, U: PartialEq, A: Allocator> PartialEq for Trailer<T, U, A> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other && self.as_slice() == other.as_slice()
+    }
+}
+
+impl<T: Eq, U: Eq, A: Allocator> Eq for Trailer<T, U, A> {}
+
+impl<T: Hash, U: Hash, A: Allocator> Hash for Trailer<T, U, A> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state);
+        self.as_slice().hash(state);
+    }
+}
+
+impl<T: PartialOrd, U: PartialOrd, A: Allocator> PartialOrd for Trailer<T, U, A> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (**self).partial_cmp(&**other) {
+            Some(Ordering::Equal) => self.as_slice().partial_cmp(other.as_slice()),
+            ordering => ordering,
+        }
+    }
+}
+
+impl<T: Ord, U: Ord, A: Allocator> Ord for Trailer<T, U, A> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (**self)
+            .cmp(&**other)
+            .then_with(|| self.as_slice().cmp(other.as_slice()))
     }
 }
 
@@ -100,6 +647,18 @@ impl<T> DerefMut for Trailer<T> {
 mod tests {
     use super::*;
 
+    // size guarantees, checked at compile time
+    const WORD: usize = mem::size_of::<usize>();
+    const _: () = assert!(mem::size_of::<Trailer<u64>>() == 2 * WORD);
+    const _: () = assert!(mem::size_of::<Option<Trailer<u64>>>() == 2 * WORD);
+    const _: () = assert!(mem::size_of::<Option<Trailer<(), u32>>>() == 2 * WORD);
+
+    // only compiles if `Trailer` is covariant over `T` and `U`
+    #[allow(dead_code)]
+    fn covariant<'a>(t: Trailer<&'static str, &'static str>) -> Trailer<&'a str, &'a str> {
+        t
+    }
+
     #[test]
     fn default() {
         #[derive(Debug, Default)]
@@ -130,7 +689,7 @@ mod tests {
 
             println!("Inner: {:?}", *a);
             println!("bytes: {:?}", a.bytes());
-            let raw = unsafe { ::std::slice::from_raw_parts(a.ptr, a.size) };
+            let raw = unsafe { ::std::slice::from_raw_parts(a.ptr.as_ptr(), a.size()) };
             println!("raw bytes: {:?}", raw);
             assert_eq!(&raw[..20], &vec![57u8, 48, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3, 4][..]);
         }
@@ -140,6 +699,7 @@ mod tests {
 
     #[test]
     fn copy() {
+        #[allow(dead_code)]
         #[derive(Debug, Clone, Copy)]
         struct Inner {
             field1: usize,
@@ -165,11 +725,351 @@ mod tests {
 
         println!("Inner: {:?}", *a);
         println!("bytes: {:?}", a.bytes());
-        let raw = unsafe { ::std::slice::from_raw_parts(a.ptr, a.size) };
+        let raw = unsafe { ::std::slice::from_raw_parts(a.ptr.as_ptr(), a.size()) };
         println!("raw bytes: {:?}", raw);
         assert_eq!(&raw[..20], &vec![46u8, 22, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3, 4][..]);
 
         assert_eq!(::std::mem::size_of::<Data>(), 16);
         assert_eq!(::std::mem::align_of::<Data>(), 8);
     }
+
+    #[test]
+    fn resize() {
+        #[derive(Debug, Default)]
+        struct Inner {
+            field1: usize,
+        }
+
+        let mut a = Trailer::<Inner>::new(4);
+        a.field1 = 42;
+        a.bytes_mut().copy_from_slice(&[1, 2, 3, 4]);
+
+        a.resize(1000);
+        assert_eq!(a.capacity(), 1000);
+        assert_eq!(a.field1, 42);
+        assert_eq!(&a.bytes()[..4], &[1, 2, 3, 4]);
+        assert!(a.bytes()[4..].iter().all(|b| *b == 0));
+
+        a.shrink_to(2000);
+        assert_eq!(a.capacity(), 1000);
+        a.shrink_to(2);
+        assert_eq!(a.capacity(), 2);
+        assert_eq!(a.bytes(), &[1, 2]);
+
+        a.reserve(3);
+        assert_eq!(a.bytes(), &[1, 2, 0, 0, 0]);
+        assert_eq!(a.field1, 42);
+    }
+
+    #[test]
+    fn typed_tail() {
+        use std::{cell::Cell, rc::Rc};
+
+        #[derive(Debug)]
+        struct Header {
+            field1: u8,
+        }
+
+        let a = Trailer::<Header, u32>::from_slice(Header { field1: 1 }, &[1, 2, 3]);
+        assert_eq!(a.field1, 1);
+        assert_eq!(a.as_slice(), &[1, 2, 3]);
+        assert_eq!(a.tail_ptr() as usize % mem::align_of::<u32>(), 0);
+        assert_eq!(a.size(), 16);
+
+        struct Counted(Rc<Cell<usize>>);
+
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let drops = Rc::new(Cell::new(0));
+        {
+            let a = Trailer::from_fn(Header { field1: 2 }, 5, |_| Counted(drops.clone()));
+            assert_eq!(a.capacity(), 5);
+        }
+        assert_eq!(drops.get(), 5);
+    }
+
+    #[test]
+    fn fallible() {
+        #[derive(Debug, Default)]
+        struct Inner {
+            field1: usize,
+        }
+
+        assert_eq!(
+            Trailer::<Inner>::try_new(usize::MAX).err(),
+            Some(TrailerAllocError::CapacityOverflow)
+        );
+        assert_eq!(
+            Trailer::<Inner>::try_new(isize::MAX as usize - 4).err(),
+            Some(TrailerAllocError::CapacityOverflow)
+        );
+
+        let mut a = Trailer::<Inner>::try_new(4).unwrap();
+        a.field1 = 1;
+        assert_eq!(
+            a.try_reserve(usize::MAX),
+            Err(TrailerAllocError::CapacityOverflow)
+        );
+        assert_eq!(a.capacity(), 4);
+        assert_eq!(a.field1, 1);
+
+        let mut empty = Trailer::<()>::try_from((), 0).unwrap();
+        assert_eq!(empty.bytes(), &[] as &[u8]);
+        empty.reserve(2);
+        assert_eq!(empty.bytes(), &[0, 0]);
+        empty.shrink_to(0);
+        assert_eq!(empty.capacity(), 0);
+    }
+
+    #[test]
+    fn panicking_default() {
+        use std::panic;
+
+        #[derive(Debug)]
+        struct Bad(#[allow(dead_code)] Box<u8>);
+
+        impl Default for Bad {
+            fn default() -> Self {
+                panic!("no default")
+            }
+        }
+
+        assert!(panic::catch_unwind(|| Trailer::<Bad>::try_new(4)).is_err());
+        assert!(panic::catch_unwind(|| Trailer::<Bad>::new(4)).is_err());
+        assert!(panic::catch_unwind(|| Trailer::<Bad>::new_in(0, Global)).is_err());
+    }
+
+    #[test]
+    fn clone_and_compare() {
+        use std::collections::{BTreeSet, HashMap};
+
+        #[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        struct Inner {
+            name: String,
+        }
+
+        let mut a = Trailer::<Inner>::new(3);
+        a.name = "a".to_string();
+        a.bytes_mut().copy_from_slice(&[1, 2, 3]);
+
+        let mut b = a.clone();
+        assert_ne!(a.ptr, b.ptr);
+        assert_eq!(a, b);
+        assert_eq!(b.name, "a");
+        assert_eq!(b.bytes(), &[1, 2, 3]);
+
+        b.bytes_mut()[2] = 4;
+        assert_ne!(a, b);
+        assert!(a < b);
+
+        let mut c = a.clone();
+        c.name = "0".to_string();
+        assert!(c < a);
+
+        let mut map = HashMap::new();
+        map.insert(a.clone(), 1);
+        map.insert(b.clone(), 2);
+        assert_eq!(map.get(&a), Some(&1));
+        assert_eq!(map.get(&b), Some(&2));
+
+        let set: BTreeSet<_> = vec![b.clone(), a.clone(), c.clone()].into_iter().collect();
+        let names: Vec<_> = set.iter().map(|t| t.bytes().to_vec()).collect();
+        assert_eq!(names, vec![vec![1, 2, 3], vec![1, 2, 3], vec![1, 2, 4]]);
+        assert_eq!(set.iter().next().unwrap().name, "0");
+    }
+
+    #[test]
+    fn owned_header() {
+        use std::sync::Arc;
+
+        #[derive(Debug)]
+        struct Inner {
+            name: String,
+            shared: Arc<()>,
+            len: usize,
+        }
+
+        let shared = Arc::new(());
+        let a = Trailer::with_header(
+            Inner {
+                name: "a".to_string(),
+                shared: shared.clone(),
+                len: 0,
+            },
+            4,
+        );
+        assert_eq!(a.name, "a");
+        assert_eq!(a.bytes(), &[0, 0, 0, 0]);
+        assert_eq!(Arc::strong_count(&shared), 2);
+
+        let b = Trailer::new_with(4, |bytes| {
+            bytes[..2].copy_from_slice(&[1, 2]);
+            Inner {
+                name: "b".to_string(),
+                shared: shared.clone(),
+                len: 2,
+            }
+        });
+        assert_eq!(b.len, 2);
+        assert_eq!(b.bytes(), &[1, 2, 0, 0]);
+        assert_eq!(Arc::strong_count(&b.shared), 3);
+
+        let c: Result<Trailer<Inner>, &str> = Trailer::try_new_with(4, |_| Err("invalid"));
+        assert_eq!(c.err(), Some("invalid"));
+
+        drop(a);
+        drop(b);
+        assert_eq!(Arc::strong_count(&shared), 1);
+    }
+
+    #[test]
+    fn send_sync() {
+        use std::{sync::RwLock, thread};
+
+        #[derive(Debug, Default)]
+        struct Inner {
+            field1: usize,
+        }
+
+        let mut a = Trailer::<Inner>::new(4);
+        a.field1 = 1;
+        let a = thread::spawn(move || {
+            a.bytes_mut()[0] = 1;
+            a
+        })
+        .join()
+        .unwrap();
+        assert_eq!(a.bytes(), &[1, 0, 0, 0]);
+
+        let lock = RwLock::new(a);
+        thread::scope(|s| {
+            s.spawn(|| lock.write().unwrap().field1 += 1);
+            s.spawn(|| assert!(lock.read().unwrap().field1 >= 1));
+        });
+        assert_eq!(lock.read().unwrap().field1, 2);
+    }
+
+    #[test]
+    fn raw() {
+        #[derive(Debug, Default)]
+        struct Inner {
+            field1: usize,
+        }
+
+        let mut a = Trailer::<Inner>::new(4);
+        a.field1 = 1;
+        unsafe { *a.as_mut_tail_ptr().add(3) = 4 };
+        assert_eq!(a.as_ptr(), &*a as *const Inner);
+        assert_eq!(a.as_tail_ptr(), a.bytes().as_ptr());
+
+        let (ptr, capacity) = a.into_raw();
+        assert_eq!(capacity, 4);
+        assert_eq!(unsafe { ptr.as_ref() }.field1, 1);
+
+        let mut a = unsafe { Trailer::<Inner>::from_raw(ptr, capacity) };
+        assert_eq!(a.field1, 1);
+        assert_eq!(a.bytes(), &[0, 0, 0, 4]);
+        a.resize(8);
+        assert_eq!(a.bytes(), &[0, 0, 0, 4, 0, 0, 0, 0]);
+
+        let (header, bytes): (&'static mut Inner, &'static mut [u8]) = a.leak();
+        header.field1 += 1;
+        bytes[0] = 1;
+        assert_eq!(header.field1, 2);
+        assert_eq!(bytes, &[1, 0, 0, 4, 0, 0, 0, 0]);
+    }
+
+    #[cfg(feature = "allocator-api2")]
+    #[test]
+    fn allocator() {
+        use allocator_api2::alloc::{AllocError, Allocator, Global, Layout};
+        use std::cell::Cell;
+
+        #[derive(Debug, Default)]
+        struct Counting {
+            allocated: Cell<usize>,
+        }
+
+        unsafe impl Allocator for &Counting {
+            fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+                self.allocated.set(self.allocated.get() + layout.size());
+                Global.allocate(layout)
+            }
+
+            unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+                self.allocated.set(self.allocated.get() - layout.size());
+                Global.deallocate(ptr, layout)
+            }
+        }
+
+        #[derive(Debug, Clone, Copy, Default, PartialEq)]
+        struct Inner {
+            field1: usize,
+        }
+
+        let counting = Counting::default();
+        {
+            let mut a = Trailer::<Inner, u8, _>::new_in(8, &counting);
+            assert_eq!(counting.allocated.get(), 8 + 8 + 8);
+            assert_eq!(mem::size_of_val(&a), 3 * mem::size_of::<usize>());
+
+            a.field1 = 1;
+            a.bytes_mut()[7] = 7;
+            a.resize(16);
+            assert_eq!(counting.allocated.get(), 8 + 8 + 16);
+            assert_eq!(a.field1, 1);
+            assert_eq!(&a.bytes()[6..], &[0, 7, 0, 0, 0, 0, 0, 0, 0, 0]);
+
+            let b = a.clone();
+            assert_eq!(counting.allocated.get(), 2 * (8 + 8 + 16));
+            assert_eq!(a, b);
+            assert!(ptr::eq(*b.allocator(), &counting));
+
+            let c = Trailer::from_in(Inner { field1: 2 }, 0, &counting);
+            assert_eq!(c.field1, 2);
+        }
+        assert_eq!(counting.allocated.get(), 0);
+
+        assert_eq!(mem::size_of::<Trailer<Inner, u8, Global>>(), 2 * WORD);
+    }
+
+    #[test]
+    fn parts() {
+        #[derive(Debug, Default)]
+        struct Inner {
+            len: usize,
+        }
+
+        fn write(mut view: TrailerMut<Inner>, data: &[u8]) {
+            let (header, bytes) = view.parts_mut();
+            bytes[header.len..header.len + data.len()].copy_from_slice(data);
+            header.len += data.len();
+        }
+
+        fn written(view: TrailerRef<Inner>) -> Vec<u8> {
+            view.bytes()[..view.len].to_vec()
+        }
+
+        let mut a = Trailer::<Inner>::new(8);
+        {
+            let (header, bytes) = a.parts_mut();
+            bytes[0] = 1;
+            header.len = 1;
+        }
+        write(a.view_mut(), &[2, 3]);
+        write((&mut a).into(), &[4]);
+        assert_eq!(a.len, 4);
+        assert_eq!(written(a.view()), vec![1, 2, 3, 4]);
+
+        let (header, bytes) = a.parts();
+        assert_eq!(header.len, 4);
+        assert_eq!(bytes.len(), 8);
+
+        let thin = ThinTrailer::from(a);
+        assert_eq!(written((&thin).into()), vec![1, 2, 3, 4]);
+    }
 }
@@ -0,0 +1,323 @@
+use std::{
+    alloc::{self, Layout},
+    fmt,
+    marker::PhantomData,
+    mem,
+    ops::{Deref, DerefMut, Range},
+    ptr::{self, NonNull},
+    slice,
+};
+
+use crate::{Trailer, TrailerAllocError};
+
+/// a header followed by `N` byte regions of different sizes, in a single
+/// allocation
+///
+/// the offset and length of each region is stored next to the pointer, so
+/// regions can be accessed by index without parsing the header
+///
+/// the allocation is laid out like a `Trailer<T>`, but its alignment is
+/// raised to the largest alignment requested for a region
+pub struct MultiTrailer<T, const N: usize> {
+    // points to the header, never null
+    ptr: NonNull<u8>,
+    capacity: usize,
+    // alignment of the allocation
+    align: usize,
+    regions: [(usize, usize); N],
+    phantom: PhantomData<T>,
+}
+
+unsafe impl<T: Send, const N: usize> Send for MultiTrailer<T, N> {}
+unsafe impl<T: Sync, const N: usize> Sync for MultiTrailer<T, N> {}
+
+impl<T, const N: usize> MultiTrailer<T, N> {
+    /// allocates a zeroed region of each length in `lens`, one after the
+    /// other
+    pub fn with_header(t: T, lens: [usize; N]) -> MultiTrailer<T, N> {
+        let layouts = lens.map(|len| {
+            Layout::from_size_align(len, 1)
+                .unwrap_or_else(|_| TrailerAllocError::CapacityOverflow.handle())
+        });
+        MultiTrailer::with_layouts(t, layouts)
+    }
+
+    /// allocates a zeroed region of each size in `layouts`, with each region
+    /// starting at an address aligned to its layout's alignment
+    pub fn with_layouts(t: T, layouts: [Layout; N]) -> MultiTrailer<T, N> {
+        MultiTrailer::try_with_layouts(t, layouts).unwrap_or_else(|e| e.handle())
+    }
+
+    /// like `with_layouts`, but returns an error instead of panicking if
+    /// the allocation fails or its size overflows
+    pub fn try_with_layouts(
+        t: T,
+        layouts: [Layout; N],
+    ) -> Result<MultiTrailer<T, N>, TrailerAllocError> {
+        let (header_layout, _, tail_offset) = Trailer::<T>::layout(0)?;
+        let align = layouts
+            .iter()
+            .map(Layout::align)
+            .fold(header_layout.align(), usize::max);
+
+        // the allocation is aligned to `align`, so the padding of a region
+        // only depends on its offset from the start of the allocation
+        let mut end = 0usize;
+        let mut regions = [(0, 0); N];
+        for (region, layout) in regions.iter_mut().zip(layouts.iter()) {
+            let misalignment = (tail_offset + end) & (layout.align() - 1);
+            let padding = (layout.align() - misalignment) & (layout.align() - 1);
+            let offset = end
+                .checked_add(padding)
+                .ok_or(TrailerAllocError::CapacityOverflow)?;
+            end = offset
+                .checked_add(layout.size())
+                .ok_or(TrailerAllocError::CapacityOverflow)?;
+            *region = (offset, layout.size());
+        }
+
+        let (layout, header_offset) = MultiTrailer::<T, N>::layout(end, align)?;
+        unsafe {
+            let base = alloc::alloc_zeroed(layout);
+            if base.is_null() {
+                return Err(TrailerAllocError::AllocError { layout });
+            }
+            // the same prefix as a `Trailer`, so that `into_trailer` can
+            // reuse the allocation
+            (base as *mut usize).write(end);
+            let ptr = base.add(header_offset);
+            (ptr as *mut T).write(t);
+
+            Ok(MultiTrailer {
+                ptr: NonNull::new_unchecked(ptr),
+                capacity: end,
+                align,
+                regions,
+                phantom: PhantomData,
+            })
+        }
+    }
+
+    /// the layout of a `Trailer<T>` with this capacity, with its alignment
+    /// raised to `align`, and the offset of the header
+    fn layout(capacity: usize, align: usize) -> Result<(Layout, usize), TrailerAllocError> {
+        let (layout, header_offset, _) = Trailer::<T>::layout(capacity)?;
+        let layout = layout
+            .align_to(align)
+            .map_err(|_| TrailerAllocError::CapacityOverflow)?;
+        Ok((layout, header_offset))
+    }
+
+    /// the range of the tail covered by region `index`
+    pub fn region_range(&self, index: usize) -> Range<usize> {
+        let (offset, len) = self.regions[index];
+        offset..offset + len
+    }
+
+    pub fn region(&self, index: usize) -> &[u8] {
+        &self.bytes()[self.region_range(index)]
+    }
+
+    pub fn region_mut(&mut self, index: usize) -> &mut [u8] {
+        let range = self.region_range(index);
+        &mut self.bytes_mut()[range]
+    }
+
+    pub fn regions(&self) -> [&[u8]; N] {
+        std::array::from_fn(|i| self.region(i))
+    }
+
+    /// every region, mutably borrowed at the same time
+    pub fn regions_mut(&mut self) -> [&mut [u8]; N] {
+        self.parts_mut().1
+    }
+
+    /// the header and every region, mutably borrowed at the same time
+    pub fn parts_mut(&mut self) -> (&mut T, [&mut [u8]; N]) {
+        let tail = self.tail_ptr();
+        // regions were laid out one after the other, so they do not overlap
+        // with each other or with the header
+        let regions = self
+            .regions
+            .map(|(offset, len)| unsafe { slice::from_raw_parts_mut(tail.add(offset), len) });
+        (unsafe { &mut *(self.ptr.as_ptr() as *mut T) }, regions)
+    }
+
+    // the whole tail, including the padding between regions
+    fn bytes(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.tail_ptr(), self.capacity) }
+    }
+
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.tail_ptr(), self.capacity) }
+    }
+
+    /// the size of the whole tail, including the padding between regions
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// returns a trailer whose tail holds every region and the padding
+    /// between them
+    ///
+    /// the allocation is reused if no region needed more alignment than a
+    /// `Trailer<T>` has, otherwise the header is moved and the tail copied
+    /// into a new allocation
+    pub fn into_trailer(self) -> Trailer<T> {
+        let this = mem::ManuallyDrop::new(self);
+        let (layout, header_offset) = MultiTrailer::<T, N>::layout(this.capacity, this.align)
+            .expect("the layout was checked at allocation");
+        let trailer_layout = Trailer::<T>::layout(this.capacity)
+            .expect("the layout was checked at allocation")
+            .0;
+
+        unsafe {
+            if layout == trailer_layout {
+                return Trailer::from_raw(this.ptr.cast(), this.capacity);
+            }
+
+            let trailer =
+                Trailer::<T>::try_allocate_uninit(this.capacity).unwrap_or_else(|e| e.handle());
+            ptr::copy_nonoverlapping(
+                this.ptr.as_ptr() as *const T,
+                trailer.ptr.as_ptr() as *mut T,
+                1,
+            );
+            ptr::copy_nonoverlapping(this.tail_ptr(), trailer.tail_ptr(), this.capacity);
+            // the header was moved, only the memory is released
+            alloc::dealloc(this.ptr.as_ptr().sub(header_offset), layout);
+            trailer
+        }
+    }
+
+    fn tail_ptr(&self) -> *mut u8 {
+        unsafe { self.ptr.as_ptr().add(Trailer::<T>::tail_offset()) }
+    }
+}
+
+impl<T, const N: usize> Drop for MultiTrailer<T, N> {
+    fn drop(&mut self) {
+        let (layout, header_offset) = MultiTrailer::<T, N>::layout(self.capacity, self.align)
+            .expect("the layout was checked at allocation");
+        unsafe {
+            ptr::drop_in_place(self.ptr.as_ptr() as *mut T);
+            alloc::dealloc(self.ptr.as_ptr().sub(header_offset), layout);
+        }
+    }
+}
+
+impl<T, const N: usize> Deref for MultiTrailer<T, N> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*(self.ptr.as_ptr() as *const T) }
+    }
+}
+
+impl<T, const N: usize> DerefMut for MultiTrailer<T, N> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *(self.ptr.as_ptr() as *mut T) }
+    }
+}
+
+impl<T: fmt::Debug, const N: usize> fmt::Debug for MultiTrailer<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MultiTrailer")
+            .field("header", &**self)
+            .field("regions", &self.regions())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regions() {
+        #[derive(Debug, Default)]
+        struct Record {
+            id: u16,
+        }
+
+        let mut a = MultiTrailer::<Record, 3>::with_header(Record { id: 1 }, [3, 2, 0]);
+        assert_eq!(a.capacity(), 5);
+        assert_eq!(a.region_range(1), 3..5);
+        assert_eq!(a.regions(), [&[0, 0, 0][..], &[0, 0], &[]]);
+
+        let (header, [key, value, signature]) = a.parts_mut();
+        header.id = 2;
+        key.copy_from_slice(b"key");
+        value.copy_from_slice(b"va");
+        assert!(signature.is_empty());
+        a.region_mut(1)[1] = b'l';
+        assert_eq!(a.id, 2);
+        assert_eq!(a.region(0), b"key");
+        assert_eq!(a.region(1), b"vl");
+        assert_eq!(a.into_trailer().bytes(), b"keyvl");
+    }
+
+    #[test]
+    fn aligned() {
+        let mut a = MultiTrailer::<u8, 3>::with_layouts(
+            7,
+            [
+                Layout::new::<u8>(),
+                Layout::new::<u64>(),
+                Layout::new::<u32>(),
+            ],
+        );
+        for (i, region) in a.regions_mut().iter_mut().enumerate() {
+            region[0] = i as u8 + 1;
+        }
+
+        assert_eq!(a.region(0), &[1]);
+        assert_eq!(a.region(1).len(), 8);
+        assert_eq!(a.region(1).as_ptr() as usize % 8, 0);
+        assert_eq!(a.region(2).as_ptr() as usize % 4, 0);
+        assert_eq!(a.region(2), &[3, 0, 0, 0]);
+        assert_eq!(*a, 7);
+    }
+
+    #[test]
+    fn over_aligned() {
+        let mut a = MultiTrailer::<String, 2>::with_layouts(
+            String::from("header"),
+            [
+                Layout::from_size_align(3, 1).unwrap(),
+                Layout::from_size_align(64, 64).unwrap(),
+            ],
+        );
+        assert_eq!(a.region(1).as_ptr() as usize % 64, 0);
+        a.region_mut(0).copy_from_slice(b"abc");
+        a.region_mut(1)[0] = 1;
+        let range = a.region_range(1);
+
+        // the allocation cannot be reused, so it is copied
+        let b = a.into_trailer();
+        assert_eq!(&*b, "header");
+        assert_eq!(&b.bytes()[..3], b"abc");
+        assert_eq!(b.bytes()[range.start], 1);
+
+        drop(MultiTrailer::<String, 1>::with_layouts(
+            String::from("dropped"),
+            [Layout::from_size_align(1, 128).unwrap()],
+        ));
+    }
+
+    #[test]
+    fn reused_allocation() {
+        let mut a = MultiTrailer::<u64, 2>::with_header(1, [2, 2]);
+        a.region_mut(1).copy_from_slice(&[3, 4]);
+        let header = &*a as *const u64;
+
+        let b = a.into_trailer();
+        assert_eq!(&*b as *const u64, header);
+        assert_eq!(b.bytes(), &[0, 0, 3, 4]);
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity overflow")]
+    fn capacity_overflow() {
+        MultiTrailer::<u8, 2>::with_header(0, [usize::MAX, 1]);
+    }
+}
@@ -0,0 +1,297 @@
+use std::{mem, ptr, slice};
+
+use crate::{allocator::Allocator, error::CastError, view::TrailerRef, Trailer};
+
+/// headers that can be read from and written as raw bytes
+///
+/// this is implemented for the primitive integer and float types and for
+/// arrays of them. Other types can implement it through `impl_plain_data!`,
+/// which checks the bounds of either `bytemuck` or `zerocopy`:
+///
+/// ```
+/// # #[cfg(feature = "bytemuck")] {
+/// #[derive(Clone, Copy)]
+/// #[repr(C)]
+/// struct Header {
+///     kind: u32,
+///     len: u32,
+/// }
+///
+/// unsafe impl bytemuck::Zeroable for Header {}
+/// unsafe impl bytemuck::Pod for Header {}
+///
+/// trailer::impl_plain_data!(bytemuck: Header);
+/// # }
+/// ```
+///
+/// # Safety
+///
+/// the type must not have padding, and any bit pattern must be a valid value
+pub unsafe trait PlainData: Copy + 'static {}
+
+macro_rules! plain_data_primitives {
+    ($($ty:ty),*) => {
+        $(unsafe impl PlainData for $ty {})*
+    };
+}
+
+plain_data_primitives!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+unsafe impl<T: PlainData, const N: usize> PlainData for [T; N] {}
+
+/// implements `PlainData` for types that already implement `bytemuck::Pod`,
+/// or `zerocopy::FromBytes + IntoBytes + Immutable`
+///
+/// the arm used needs the corresponding feature
+#[macro_export]
+macro_rules! impl_plain_data {
+    (bytemuck: $($ty:ty),+ $(,)?) => {
+        $(
+            const _: fn() = $crate::__private::assert_bytemuck::<$ty>;
+            unsafe impl $crate::PlainData for $ty {}
+        )+
+    };
+    (zerocopy: $($ty:ty),+ $(,)?) => {
+        $(
+            const _: fn() = $crate::__private::assert_zerocopy::<$ty>;
+            unsafe impl $crate::PlainData for $ty {}
+        )+
+    };
+}
+
+#[doc(hidden)]
+pub mod __private {
+    #[cfg(feature = "bytemuck")]
+    pub fn assert_bytemuck<T: bytemuck::Pod>() {}
+
+    #[cfg(feature = "zerocopy")]
+    pub fn assert_zerocopy<T>()
+    where
+        T: zerocopy::FromBytes + zerocopy::IntoBytes + zerocopy::Immutable,
+    {
+    }
+}
+
+impl<T: PlainData, A: Allocator> Trailer<T, u8, A> {
+    /// the header and the tail, as one contiguous byte slice
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.bytes_len()) }
+    }
+
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.bytes_len()) }
+    }
+
+    // the tail of a byte trailer directly follows the header
+    fn bytes_len(&self) -> usize {
+        mem::size_of::<T>() + self.capacity()
+    }
+}
+
+impl<T, A: Allocator> Trailer<T, u8, A> {
+    /// a reference to the `U` at `offset` in the tail
+    ///
+    /// returns `None` if it does not fit in the tail, or if it is not aligned
+    /// for `U`
+    pub fn tail_get<U: PlainData>(&self, offset: usize) -> Option<&U> {
+        let bytes = self.checked_range(offset, mem::size_of::<U>())?;
+        if bytes.as_ptr() as usize & (mem::align_of::<U>() - 1) != 0 {
+            return None;
+        }
+        Some(unsafe { &*(bytes.as_ptr() as *const U) })
+    }
+
+    pub fn tail_get_mut<U: PlainData>(&mut self, offset: usize) -> Option<&mut U> {
+        let bytes = self.checked_range_mut(offset, mem::size_of::<U>())?;
+        if bytes.as_ptr() as usize & (mem::align_of::<U>() - 1) != 0 {
+            return None;
+        }
+        Some(unsafe { &mut *(bytes.as_mut_ptr() as *mut U) })
+    }
+
+    /// copies the `U` at `offset` in the tail, whatever its alignment
+    ///
+    /// returns `None` if it does not fit in the tail
+    pub fn read_unaligned<U: PlainData>(&self, offset: usize) -> Option<U> {
+        let bytes = self.checked_range(offset, mem::size_of::<U>())?;
+        Some(unsafe { ptr::read_unaligned(bytes.as_ptr() as *const U) })
+    }
+
+    /// writes `value` at `offset` in the tail, whatever its alignment
+    ///
+    /// returns `None` without writing anything if it does not fit in the
+    /// tail
+    pub fn write_unaligned<U: PlainData>(&mut self, offset: usize, value: U) -> Option<()> {
+        let bytes = self.checked_range_mut(offset, mem::size_of::<U>())?;
+        unsafe { ptr::write_unaligned(bytes.as_mut_ptr() as *mut U, value) };
+        Some(())
+    }
+}
+
+impl<T: PlainData> Trailer<T> {
+    /// rebuilds a trailer from bytes produced by `as_bytes()`
+    ///
+    /// `bytes` holds the header followed by up to `capacity` bytes of tail,
+    /// and does not need to be aligned. The rest of the tail is zeroed
+    pub fn from_bytes(bytes: &[u8], capacity: usize) -> Result<Trailer<T>, CastError> {
+        let header_len = mem::size_of::<T>();
+        if bytes.len() < header_len {
+            return Err(CastError::TooShort {
+                len: bytes.len(),
+                min: header_len,
+            });
+        }
+        let max = header_len.saturating_add(capacity);
+        if bytes.len() > max {
+            return Err(CastError::TooLong {
+                len: bytes.len(),
+                max,
+            });
+        }
+
+        let (header, tail) = bytes.split_at(header_len);
+        let t = unsafe { ptr::read_unaligned(header.as_ptr() as *const T) };
+        let mut trailer = Trailer::with_header(t, capacity);
+        trailer.bytes_mut()[..tail.len()].copy_from_slice(tail);
+        Ok(trailer)
+    }
+}
+
+impl<'a, T: PlainData> TrailerRef<'a, T> {
+    /// borrows `bytes` as a header followed by a tail, without copying
+    ///
+    /// `bytes` must be aligned for `T` and hold at least the header
+    pub fn ref_from(bytes: &'a [u8]) -> Result<TrailerRef<'a, T>, CastError> {
+        let header_len = mem::size_of::<T>();
+        if bytes.len() < header_len {
+            return Err(CastError::TooShort {
+                len: bytes.len(),
+                min: header_len,
+            });
+        }
+        let align = mem::align_of::<T>();
+        if bytes.as_ptr() as usize & (align - 1) != 0 {
+            return Err(CastError::Misaligned { align });
+        }
+
+        let (header, tail) = bytes.split_at(header_len);
+        let header = unsafe { &*(header.as_ptr() as *const T) };
+        Ok(TrailerRef::new(header, tail))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut a = Trailer::<[u32; 2]>::from([1, 2], 3);
+        a.bytes_mut().copy_from_slice(&[7, 8, 9]);
+
+        let bytes = a.as_bytes().to_vec();
+        assert_eq!(bytes.len(), 11);
+        assert_eq!(&bytes[8..], &[7, 8, 9]);
+        a.as_bytes_mut()[..4].copy_from_slice(&5u32.to_ne_bytes());
+        assert_eq!(*a, [5, 2]);
+
+        let b = Trailer::<[u32; 2]>::from_bytes(&bytes, 5).unwrap();
+        assert_eq!(*b, [1, 2]);
+        assert_eq!(b.bytes(), &[7, 8, 9, 0, 0]);
+        // unaligned input is copied
+        let mut shifted = vec![0];
+        shifted.extend_from_slice(&bytes);
+        let c = Trailer::<[u32; 2]>::from_bytes(&shifted[1..], 3).unwrap();
+        assert_eq!(b.as_slice()[..3], *c.bytes());
+
+        assert_eq!(
+            Trailer::<[u32; 2]>::from_bytes(&bytes[..7], 3).unwrap_err(),
+            CastError::TooShort { len: 7, min: 8 }
+        );
+        assert_eq!(
+            Trailer::<[u32; 2]>::from_bytes(&bytes, 2).unwrap_err(),
+            CastError::TooLong { len: 11, max: 10 }
+        );
+
+        let view = TrailerRef::<[u32; 2]>::ref_from(a.as_bytes()).unwrap();
+        assert_eq!(*view, [5, 2]);
+        assert_eq!(view.bytes(), &[7, 8, 9]);
+        let unaligned = &a.as_bytes()[1..];
+        assert_eq!(
+            TrailerRef::<[u32; 2]>::ref_from(unaligned).unwrap_err(),
+            CastError::Misaligned { align: 4 }
+        );
+        assert!(matches!(
+            TrailerRef::<[u32; 2]>::ref_from(&a.as_bytes()[..4]),
+            Err(CastError::TooShort { len: 4, min: 8 })
+        ));
+    }
+
+    #[test]
+    fn typed_access() {
+        // the tail of a `Trailer<u64>` starts 8 bytes aligned
+        let mut a = Trailer::<u64>::from(0, 16);
+        *a.tail_get_mut::<u32>(4).unwrap() = 0x0102_0304;
+        assert_eq!(a.tail_get::<u32>(4), Some(&0x0102_0304));
+        assert_eq!(a.read_unaligned::<u32>(4), Some(0x0102_0304));
+        assert_eq!(a.tail_get::<u32>(2), None);
+        assert_eq!(a.tail_get::<u64>(12), None);
+        assert!(a.tail_get_mut::<u16>(15).is_none());
+
+        a.write_unaligned::<u32>(1, 7).unwrap();
+        assert_eq!(a.read_unaligned::<u32>(1), Some(7));
+        assert_eq!(a.read_unaligned::<u64>(9), None);
+        assert_eq!(a.write_unaligned::<u64>(9, 1), None);
+        assert_eq!(a.read_unaligned::<[u8; 2]>(14), Some([0, 0]));
+
+        // the header's own `get` is not shadowed
+        let v = Trailer::with_header(vec![1u32, 2], 4);
+        assert_eq!(v.get(1), Some(&2));
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn bytemuck_header() {
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        #[repr(C)]
+        struct Header {
+            kind: u16,
+            len: u16,
+        }
+
+        unsafe impl bytemuck::Zeroable for Header {}
+        unsafe impl bytemuck::Pod for Header {}
+
+        impl_plain_data!(bytemuck: Header);
+
+        let a = Trailer::from(Header { kind: 1, len: 2 }, 2);
+        assert_eq!(a.as_bytes().len(), 6);
+        let b = Trailer::<Header>::from_bytes(a.as_bytes(), 2).unwrap();
+        assert_eq!(*b, Header { kind: 1, len: 2 });
+    }
+
+    #[cfg(feature = "zerocopy")]
+    #[test]
+    fn zerocopy_header() {
+        #[derive(
+            Debug,
+            Clone,
+            Copy,
+            PartialEq,
+            zerocopy::FromBytes,
+            zerocopy::IntoBytes,
+            zerocopy::Immutable,
+        )]
+        #[repr(C)]
+        struct Header {
+            kind: u16,
+            len: u16,
+        }
+
+        impl_plain_data!(zerocopy: Header);
+
+        let a = Trailer::from(Header { kind: 1, len: 2 }, 2);
+        let view = TrailerRef::<Header>::ref_from(a.as_bytes()).unwrap();
+        assert_eq!(*view, Header { kind: 1, len: 2 });
+    }
+}
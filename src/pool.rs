@@ -0,0 +1,246 @@
+use std::{
+    fmt, mem,
+    ops::{Deref, DerefMut},
+    sync::Mutex,
+};
+
+use crate::{Trailer, TrailerAllocError};
+
+/// counters describing how a `TrailerPool` was used
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// trailers handed out from a free list
+    pub hits: usize,
+    /// trailers that had to be allocated
+    pub misses: usize,
+    /// size of the allocations kept in the free lists
+    pub retained_bytes: usize,
+}
+
+/// recycles trailer allocations instead of freeing them
+///
+/// capacities are rounded up to a power of two, and freed trailers are kept
+/// in one free list per power of two, until `max_retained_bytes` is reached.
+/// When a trailer comes back to the pool, its header is reset with the
+/// function given to `new`, while the tail keeps its previous contents
+pub struct TrailerPool<T> {
+    state: Mutex<PoolState<T>>,
+    reset: fn(&mut T),
+    max_retained_bytes: usize,
+}
+
+struct PoolState<T> {
+    // indexed by the log2 of the capacity
+    free_lists: Vec<Vec<Trailer<T>>>,
+    stats: PoolStats,
+}
+
+impl<T: Default> TrailerPool<T> {
+    pub fn new(max_retained_bytes: usize, reset: fn(&mut T)) -> TrailerPool<T> {
+        TrailerPool {
+            state: Mutex::new(PoolState {
+                free_lists: (0..usize::BITS).map(|_| Vec::new()).collect(),
+                stats: PoolStats::default(),
+            }),
+            reset,
+            max_retained_bytes,
+        }
+    }
+
+    /// returns a trailer with at least `capacity` bytes in its tail
+    ///
+    /// # Panics
+    ///
+    /// panics if `capacity` rounded up to a power of two overflows
+    pub fn get(&self, capacity: usize) -> PooledTrailer<'_, T> {
+        let capacity = capacity
+            .checked_next_power_of_two()
+            .ok_or(TrailerAllocError::CapacityOverflow)
+            .unwrap_or_else(|e| e.handle());
+        let class = capacity.trailing_zeros() as usize;
+
+        let recycled = {
+            let mut state = self.state.lock().unwrap();
+            let recycled = state.free_lists[class].pop();
+            match &recycled {
+                Some(trailer) => {
+                    state.stats.hits += 1;
+                    state.stats.retained_bytes -= trailer.current_layout().size();
+                }
+                None => state.stats.misses += 1,
+            }
+            recycled
+        };
+
+        PooledTrailer {
+            trailer: mem::ManuallyDrop::new(recycled.unwrap_or_else(|| Trailer::new(capacity))),
+            pool: self,
+        }
+    }
+}
+
+impl<T> TrailerPool<T> {
+    pub fn stats(&self) -> PoolStats {
+        self.state.lock().unwrap().stats
+    }
+
+    /// frees every trailer kept in the free lists
+    pub fn clear(&self) {
+        let free_lists = {
+            let mut state = self.state.lock().unwrap();
+            state.stats.retained_bytes = 0;
+            state
+                .free_lists
+                .iter_mut()
+                .map(mem::take)
+                .collect::<Vec<_>>()
+        };
+        drop(free_lists);
+    }
+
+    fn recycle(&self, mut trailer: Trailer<T>) {
+        // a trailer that was resized goes to the largest class it can serve
+        let capacity = trailer.capacity();
+        if capacity == 0 {
+            return;
+        }
+        let class = (usize::BITS - 1 - capacity.leading_zeros()) as usize;
+        let size = trailer.current_layout().size();
+
+        (self.reset)(&mut trailer);
+
+        let mut state = self.state.lock().unwrap();
+        if state.stats.retained_bytes + size <= self.max_retained_bytes {
+            state.stats.retained_bytes += size;
+            state.free_lists[class].push(trailer);
+        }
+    }
+}
+
+impl<T> fmt::Debug for TrailerPool<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TrailerPool")
+            .field("max_retained_bytes", &self.max_retained_bytes)
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+/// a trailer borrowed from a `TrailerPool`, returned to it on drop
+pub struct PooledTrailer<'a, T> {
+    trailer: mem::ManuallyDrop<Trailer<T>>,
+    pool: &'a TrailerPool<T>,
+}
+
+impl<'a, T> PooledTrailer<'a, T> {
+    /// detaches the trailer from the pool
+    pub fn into_inner(self) -> Trailer<T> {
+        let mut this = mem::ManuallyDrop::new(self);
+        unsafe { mem::ManuallyDrop::take(&mut this.trailer) }
+    }
+}
+
+impl<'a, T> Drop for PooledTrailer<'a, T> {
+    fn drop(&mut self) {
+        let trailer = unsafe { mem::ManuallyDrop::take(&mut self.trailer) };
+        self.pool.recycle(trailer);
+    }
+}
+
+impl<'a, T> Deref for PooledTrailer<'a, T> {
+    type Target = Trailer<T>;
+    fn deref(&self) -> &Trailer<T> {
+        &self.trailer
+    }
+}
+
+impl<'a, T> DerefMut for PooledTrailer<'a, T> {
+    fn deref_mut(&mut self) -> &mut Trailer<T> {
+        &mut self.trailer
+    }
+}
+
+impl<'a, T: fmt::Debug> fmt::Debug for PooledTrailer<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&*self.trailer, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct Inner {
+        len: usize,
+    }
+
+    #[test]
+    fn recycle() {
+        let pool = TrailerPool::<Inner>::new(1024, |inner| inner.len = 0);
+
+        let mut a = pool.get(100);
+        assert_eq!(a.capacity(), 128);
+        a.len = 3;
+        a.bytes_mut()[..3].copy_from_slice(&[1, 2, 3]);
+        let ptr = a.as_ptr();
+        drop(a);
+
+        let stats = pool.stats();
+        assert_eq!(stats.hits, 0);
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.retained_bytes, 8 + 8 + 128);
+
+        let b = pool.get(65);
+        assert_eq!(b.as_ptr(), ptr);
+        assert_eq!(b.len, 0);
+        assert_eq!(b.capacity(), 128);
+        assert_eq!(pool.stats().hits, 1);
+        assert_eq!(pool.stats().retained_bytes, 0);
+
+        // another class
+        let c = pool.get(10);
+        assert_eq!(c.capacity(), 16);
+        assert_eq!(pool.stats().misses, 2);
+
+        let b = b.into_inner();
+        drop(c);
+        drop(b);
+        assert_eq!(pool.stats().retained_bytes, 8 + 8 + 16);
+
+        pool.clear();
+        assert_eq!(pool.stats().retained_bytes, 0);
+        assert_ne!(pool.get(16).capacity(), 0);
+        assert_eq!(pool.stats().misses, 3);
+    }
+
+    #[test]
+    fn max_retained() {
+        let pool = TrailerPool::<Inner>::new(300, |_| {});
+
+        let a = pool.get(200);
+        let b = pool.get(200);
+        drop(a);
+        drop(b);
+        assert_eq!(pool.stats().retained_bytes, 8 + 8 + 256);
+
+        let mut c = pool.get(256);
+        c.resize(600);
+        drop(c);
+        assert_eq!(pool.stats().retained_bytes, 0);
+
+        let d = pool.get(512);
+        assert_eq!(pool.stats().hits, 1);
+        drop(d);
+        assert_eq!(pool.stats().retained_bytes, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity overflow")]
+    fn capacity_overflow() {
+        let pool = TrailerPool::<Inner>::new(1024, |inner| inner.len = 0);
+        drop(pool.get(1));
+        // must not be served from the class of 1 byte trailers
+        pool.get(usize::MAX);
+    }
+}
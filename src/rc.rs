@@ -0,0 +1,279 @@
+//! single-threaded reference-counted trailers
+
+use std::{cell::Cell, fmt, marker::PhantomData, mem, ops::Deref, process, ptr, slice};
+
+use crate::Trailer;
+
+/// header of the allocation: the counts are stored in front of `T`, and the
+/// tail follows it like in a `Trailer<RcInner<T>>`
+#[repr(C)]
+struct RcInner<T> {
+    strong: Cell<usize>,
+    // all the strong references together hold one weak reference
+    weak: Cell<usize>,
+    header: T,
+}
+
+/// a single-threaded reference-counted trailer, with the counts, the header
+/// and the tail in one allocation
+pub struct RcTrailer<T> {
+    ptr: *mut u8,
+    capacity: usize,
+    phantom: PhantomData<RcInner<T>>,
+}
+
+/// a non owning reference to an `RcTrailer`
+pub struct Weak<T> {
+    ptr: *mut u8,
+    capacity: usize,
+    phantom: PhantomData<RcInner<T>>,
+}
+
+impl<T> RcTrailer<T> {
+    pub fn with_header(t: T, capacity: usize) -> RcTrailer<T> {
+        RcTrailer::new_with(capacity, |_| t)
+    }
+
+    /// fills the zeroed tail first, then builds the header from it
+    pub fn new_with<F: FnOnce(&mut [u8]) -> T>(capacity: usize, f: F) -> RcTrailer<T> {
+        let trailer = mem::ManuallyDrop::new(Trailer::new_with(capacity, |bytes| RcInner {
+            strong: Cell::new(1),
+            weak: Cell::new(1),
+            header: f(bytes),
+        }));
+
+        RcTrailer {
+            ptr: trailer.ptr.as_ptr(),
+            capacity: trailer.capacity,
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.tail_ptr(), self.capacity) }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn strong_count(this: &Self) -> usize {
+        this.inner().strong.get()
+    }
+
+    pub fn weak_count(this: &Self) -> usize {
+        this.inner().weak.get() - 1
+    }
+
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        this.ptr == other.ptr
+    }
+
+    pub fn downgrade(this: &Self) -> Weak<T> {
+        increment(&this.inner().weak);
+        Weak {
+            ptr: this.ptr,
+            capacity: this.capacity,
+            phantom: PhantomData,
+        }
+    }
+
+    /// mutable access to the header and the tail, if there is no other
+    /// strong or weak reference
+    pub fn get_mut(this: &mut Self) -> Option<(&mut T, &mut [u8])> {
+        if this.is_unique() {
+            Some(unsafe { this.parts_mut_unchecked() })
+        } else {
+            None
+        }
+    }
+
+    fn is_unique(&self) -> bool {
+        self.inner().strong.get() == 1 && self.inner().weak.get() == 1
+    }
+
+    unsafe fn parts_mut_unchecked(&mut self) -> (&mut T, &mut [u8]) {
+        let header = &mut (*(self.ptr as *mut RcInner<T>)).header;
+        let bytes = slice::from_raw_parts_mut(self.tail_ptr(), self.capacity);
+        (header, bytes)
+    }
+
+    fn inner(&self) -> &RcInner<T> {
+        unsafe { &*(self.ptr as *const RcInner<T>) }
+    }
+
+    fn tail_ptr(&self) -> *mut u8 {
+        unsafe { self.ptr.add(Trailer::<RcInner<T>>::tail_offset()) }
+    }
+}
+
+impl<T: Clone> RcTrailer<T> {
+    /// mutable access to the header and the tail, copying them to a new
+    /// allocation first if they are shared
+    pub fn make_mut(this: &mut Self) -> (&mut T, &mut [u8]) {
+        if !this.is_unique() {
+            let bytes = this.bytes();
+            *this = RcTrailer::new_with(this.capacity, |tail| {
+                tail.copy_from_slice(bytes);
+                (**this).clone()
+            });
+        }
+
+        unsafe { this.parts_mut_unchecked() }
+    }
+}
+
+fn increment(count: &Cell<usize>) {
+    // the count cannot overflow before memory runs out, unless references
+    // are leaked with `mem::forget`
+    if count.get() == usize::MAX {
+        process::abort();
+    }
+    count.set(count.get() + 1);
+}
+
+impl<T> Clone for RcTrailer<T> {
+    fn clone(&self) -> Self {
+        increment(&self.inner().strong);
+        RcTrailer {
+            ptr: self.ptr,
+            capacity: self.capacity,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> Deref for RcTrailer<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.inner().header
+    }
+}
+
+impl<T> Drop for RcTrailer<T> {
+    fn drop(&mut self) {
+        let strong = &self.inner().strong;
+        strong.set(strong.get() - 1);
+        if strong.get() != 0 {
+            return;
+        }
+
+        unsafe { ptr::drop_in_place(&mut (*(self.ptr as *mut RcInner<T>)).header) };
+
+        // releases the weak reference held by the strong ones
+        drop(Weak::<T> {
+            ptr: self.ptr,
+            capacity: self.capacity,
+            phantom: PhantomData,
+        });
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for RcTrailer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RcTrailer")
+            .field("header", &**self)
+            .field("bytes", &self.bytes())
+            .finish()
+    }
+}
+
+impl<T> Weak<T> {
+    /// gets a strong reference back if the trailer was not dropped yet
+    pub fn upgrade(&self) -> Option<RcTrailer<T>> {
+        let strong = &self.inner().strong;
+        if strong.get() == 0 {
+            return None;
+        }
+
+        increment(strong);
+        Some(RcTrailer {
+            ptr: self.ptr,
+            capacity: self.capacity,
+            phantom: PhantomData,
+        })
+    }
+
+    pub fn strong_count(&self) -> usize {
+        self.inner().strong.get()
+    }
+
+    // the counts stay valid as long as there is a weak reference, but not the
+    // header
+    fn inner(&self) -> &RcInner<()> {
+        unsafe { &*(self.ptr as *const RcInner<()>) }
+    }
+}
+
+impl<T> Clone for Weak<T> {
+    fn clone(&self) -> Self {
+        increment(&self.inner().weak);
+        Weak {
+            ptr: self.ptr,
+            capacity: self.capacity,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> Drop for Weak<T> {
+    fn drop(&mut self) {
+        let weak = &self.inner().weak;
+        weak.set(weak.get() - 1);
+        if weak.get() != 0 {
+            return;
+        }
+
+        unsafe { Trailer::<RcInner<T>>::deallocate(self.ptr, self.capacity) };
+    }
+}
+
+impl<T> fmt::Debug for Weak<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "(Weak)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Inner {
+        name: String,
+    }
+
+    #[test]
+    fn counts() {
+        let mut a = RcTrailer::new_with(2, |bytes| {
+            bytes[0] = 1;
+            Inner {
+                name: "a".to_string(),
+            }
+        });
+        assert_eq!(a.bytes(), &[1, 0]);
+        RcTrailer::get_mut(&mut a).unwrap().0.name.push('b');
+        assert_eq!(a.name, "ab");
+
+        let b = a.clone();
+        let weak = RcTrailer::downgrade(&a);
+        assert_eq!(RcTrailer::strong_count(&a), 2);
+        assert_eq!(RcTrailer::weak_count(&a), 1);
+        assert!(RcTrailer::get_mut(&mut a).is_none());
+
+        {
+            let (header, bytes) = RcTrailer::make_mut(&mut a);
+            header.name.push('c');
+            bytes[1] = 2;
+        }
+        assert!(!RcTrailer::ptr_eq(&a, &b));
+        assert_eq!(a.name, "abc");
+        assert_eq!(a.bytes(), &[1, 2]);
+        assert_eq!(b.name, "ab");
+        assert_eq!(b.bytes(), &[1, 0]);
+
+        assert!(weak.upgrade().is_some());
+        drop(b);
+        assert!(weak.upgrade().is_none());
+    }
+}
@@ -0,0 +1,98 @@
+use std::io::Read;
+
+use crate::{error::ReadFrameError, Trailer};
+
+impl<T> Trailer<T> {
+    /// reads a frame made of a fixed size header followed by a payload
+    ///
+    /// the `N` header bytes are passed to `decode`, which returns the header
+    /// and the payload length. The payload is then read into a tail of
+    /// exactly that length, which must not be over `max_len`
+    ///
+    /// ```
+    /// use trailer::Trailer;
+    ///
+    /// let input: &[u8] = &[0, 3, b'a', b'b', b'c'];
+    /// let frame = Trailer::read_from(input, 1024, |header: [u8; 2]| {
+    ///     let len = u16::from_be_bytes(header);
+    ///     (len, len as usize)
+    /// })
+    /// .unwrap();
+    /// assert_eq!(*frame, 3);
+    /// assert_eq!(frame.bytes(), b"abc");
+    /// ```
+    pub fn read_from<R, F, const N: usize>(
+        mut reader: R,
+        max_len: usize,
+        decode: F,
+    ) -> Result<Trailer<T>, ReadFrameError>
+    where
+        R: Read,
+        F: FnOnce([u8; N]) -> (T, usize),
+    {
+        let mut header = [0; N];
+        reader.read_exact(&mut header)?;
+
+        let (t, len) = decode(header);
+        if len > max_len {
+            return Err(ReadFrameError::TooLong { len, max: max_len });
+        }
+
+        let mut trailer = unsafe {
+            let trailer = Trailer::<T>::try_allocate(len)?;
+            (trailer.ptr.as_ptr() as *mut T).write(t);
+            trailer
+        };
+        reader.read_exact(trailer.bytes_mut())?;
+
+        Ok(trailer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Header {
+        kind: u8,
+        len: u32,
+    }
+
+    fn decode(header: [u8; 5]) -> (Header, usize) {
+        let len = u32::from_le_bytes([header[1], header[2], header[3], header[4]]);
+        (
+            Header {
+                kind: header[0],
+                len,
+            },
+            len as usize,
+        )
+    }
+
+    #[test]
+    fn read_frame() {
+        let input: &[u8] = &[7, 3, 0, 0, 0, 1, 2, 3, 9, 9];
+        let mut reader = input;
+        let a = Trailer::read_from(&mut reader, 16, decode).unwrap();
+        assert_eq!(*a, Header { kind: 7, len: 3 });
+        assert_eq!(a.bytes(), &[1, 2, 3]);
+        assert_eq!(reader, &[9, 9]);
+
+        let empty = Trailer::read_from(&[1u8, 0, 0, 0, 0][..], 16, decode).unwrap();
+        assert_eq!(empty.capacity(), 0);
+
+        match Trailer::read_from(&input[..3], 16, decode) {
+            Err(ReadFrameError::Truncated) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match Trailer::read_from(&input[..7], 16, decode) {
+            Err(ReadFrameError::Truncated) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match Trailer::read_from(&[0u8, 0, 1, 0, 0][..], 16, decode) {
+            Err(ReadFrameError::TooLong { len: 256, max: 16 }) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,303 @@
+//! `Serialize` and `Deserialize` implementations for `Trailer`
+//!
+//! a `Trailer<T>` is serialized as a pair of the header and the tail bytes,
+//! and deserialized into a single allocation sized from the number of bytes.
+//! When the header already holds the tail length, the [`with_len`] module can
+//! be used with `#[serde(with = "trailer::serde::with_len")]` to avoid
+//! storing it twice
+use std::{cmp, fmt, marker::PhantomData};
+
+use ::serde::{
+    de::{self, DeserializeSeed, Deserializer, SeqAccess, Visitor},
+    ser::{self, SerializeTuple, Serializer},
+    Deserialize, Serialize,
+};
+
+use crate::{allocator::Allocator, Trailer, TrailerHeader, TrailerVec};
+
+// lengths read from the input are untrusted, so like serde's
+// `size_hint::cautious`, they never preallocate more than this. The tail grows
+// as the bytes actually arrive
+const MAX_PREALLOC: usize = 1 << 20;
+
+/// allocates a trailer holding `header`, reporting allocation failures as
+/// deserialization errors
+fn try_with_header<T, E: de::Error>(header: T, capacity: usize) -> Result<Trailer<T>, E> {
+    unsafe {
+        let trailer = Trailer::<T>::try_allocate(capacity).map_err(E::custom)?;
+        (trailer.ptr.as_ptr() as *mut T).write(header);
+        Ok(trailer)
+    }
+}
+
+fn tail_vec<T, E: de::Error>(header: T, len_hint: usize) -> Result<TrailerVec<T>, E> {
+    try_with_header(header, cmp::min(len_hint, MAX_PREALLOC)).map(TrailerVec::from_trailer)
+}
+
+fn push<T, E: de::Error>(vec: &mut TrailerVec<T>, byte: u8) -> Result<(), E> {
+    vec.try_reserve(1).map_err(E::custom)?;
+    vec.push(byte);
+    Ok(())
+}
+
+impl<T: Serialize, A: Allocator> Serialize for Trailer<T, u8, A> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(2)?;
+        tuple.serialize_element(&**self)?;
+        tuple.serialize_element(&Bytes(self.bytes()))?;
+        tuple.end()
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Trailer<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_tuple(2, TrailerVisitor(PhantomData))
+    }
+}
+
+struct Bytes<'a>(&'a [u8]);
+
+impl<'a> Serialize for Bytes<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+struct TrailerVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Deserialize<'de>> Visitor<'de> for TrailerVisitor<T> {
+    type Value = Trailer<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a header and a byte sequence")
+    }
+
+    fn visit_seq<S: SeqAccess<'de>>(self, mut seq: S) -> Result<Trailer<T>, S::Error> {
+        let header = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        seq.next_element_seed(TailSeed(header))?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))
+    }
+}
+
+/// deserializes the tail bytes straight into a trailer holding the header
+struct TailSeed<T>(T);
+
+impl<'de, T> DeserializeSeed<'de> for TailSeed<T> {
+    type Value = Trailer<T>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Trailer<T>, D::Error> {
+        deserializer.deserialize_bytes(self)
+    }
+}
+
+impl<'de, T> Visitor<'de> for TailSeed<T> {
+    type Value = Trailer<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a byte sequence")
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Trailer<T>, E> {
+        let mut trailer = try_with_header(self.0, v.len())?;
+        trailer.bytes_mut().copy_from_slice(v);
+        Ok(trailer)
+    }
+
+    fn visit_seq<S: SeqAccess<'de>>(self, mut seq: S) -> Result<Trailer<T>, S::Error> {
+        let mut vec = tail_vec(self.0, seq.size_hint().unwrap_or(0))?;
+        while let Some(byte) = seq.next_element()? {
+            push(&mut vec, byte)?;
+        }
+        Ok(vec.into_trailer())
+    }
+}
+
+/// serializes a trailer as its header followed by `payload_len()` bytes,
+/// without a separate length for the tail
+///
+/// the bytes after `payload_len()` are not serialized, and the deserialized
+/// trailer has a capacity of exactly `payload_len()`
+///
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// use trailer::{Trailer, TrailerHeader};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Frame {
+///     len: usize,
+/// }
+///
+/// impl TrailerHeader for Frame {
+///     fn payload_len(&self) -> usize {
+///         self.len
+///     }
+/// }
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Message {
+///     #[serde(with = "trailer::serde::with_len")]
+///     frame: Trailer<Frame>,
+/// }
+/// ```
+pub mod with_len {
+    use super::*;
+
+    pub fn serialize<T, A, S>(trailer: &Trailer<T, u8, A>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Serialize + TrailerHeader,
+        A: Allocator,
+        S: Serializer,
+    {
+        let len = trailer.payload_len();
+        if len > trailer.capacity() {
+            return Err(ser::Error::custom(format_args!(
+                "tail length {} is over the capacity of {}",
+                len,
+                trailer.capacity()
+            )));
+        }
+
+        let mut tuple = serializer.serialize_tuple(len + 1)?;
+        tuple.serialize_element(&**trailer)?;
+        for byte in &trailer.bytes()[..len] {
+            tuple.serialize_element(byte)?;
+        }
+        tuple.end()
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Trailer<T>, D::Error>
+    where
+        T: Deserialize<'de> + TrailerHeader,
+        D: Deserializer<'de>,
+    {
+        // the real length is only known once the header is read, formats
+        // that need it upfront stop reading when the visitor returns
+        deserializer.deserialize_tuple(usize::MAX, WithLenVisitor(PhantomData))
+    }
+
+    struct WithLenVisitor<T>(PhantomData<T>);
+
+    impl<'de, T: Deserialize<'de> + TrailerHeader> Visitor<'de> for WithLenVisitor<T> {
+        type Value = Trailer<T>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a header followed by its tail bytes")
+        }
+
+        fn visit_seq<S: SeqAccess<'de>>(self, mut seq: S) -> Result<Trailer<T>, S::Error> {
+            let header: T = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+            let len = header.payload_len();
+
+            let mut vec = tail_vec(header, len)?;
+            for i in 0..len {
+                let byte = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(i + 1, &self))?;
+                push(&mut vec, byte)?;
+            }
+            Ok(vec.into_trailer())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Inner {
+        id: u32,
+        len: usize,
+    }
+
+    impl TrailerHeader for Inner {
+        fn payload_len(&self) -> usize {
+            self.len
+        }
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Message {
+        #[serde(with = "with_len")]
+        frame: Trailer<Inner>,
+        end: u8,
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut a = Trailer::with_header(Inner { id: 1, len: 2 }, 3);
+        a.bytes_mut().copy_from_slice(&[1, 2, 3]);
+
+        let json = serde_json::to_string(&a).unwrap();
+        assert_eq!(json, r#"[{"id":1,"len":2},[1,2,3]]"#);
+        let b: Trailer<Inner> = serde_json::from_str(&json).unwrap();
+        assert_eq!(a, b);
+
+        let encoded = bincode::serialize(&a).unwrap();
+        let b: Trailer<Inner> = bincode::deserialize(&encoded).unwrap();
+        assert_eq!(a, b);
+        let b: Trailer<Inner> = bincode::deserialize_from(&encoded[..]).unwrap();
+        assert_eq!(a, b);
+
+        assert!(serde_json::from_str::<Trailer<Inner>>(r#"[{"id":1,"len":2}]"#).is_err());
+    }
+
+    #[test]
+    fn with_len() {
+        let mut frame = Trailer::with_header(Inner { id: 1, len: 2 }, 3);
+        frame.bytes_mut().copy_from_slice(&[1, 2, 3]);
+        let message = Message { frame, end: 9 };
+
+        let json = serde_json::to_string(&message).unwrap();
+        assert_eq!(json, r#"{"frame":[{"id":1,"len":2},1,2],"end":9}"#);
+        let decoded: Message = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.frame.bytes(), &[1, 2]);
+        assert_eq!(decoded.end, 9);
+
+        let encoded = bincode::serialize(&message).unwrap();
+        // 4 bytes of id, 8 bytes of len, the 2 tail bytes and `end`
+        assert_eq!(encoded.len(), 15);
+        let decoded: Message = bincode::deserialize(&encoded).unwrap();
+        assert_eq!(decoded.frame.bytes(), &[1, 2]);
+        assert_eq!(decoded.end, 9);
+
+        assert!(
+            serde_json::from_str::<Message>(r#"{"frame":[{"id":1,"len":2},1],"end":9}"#).is_err()
+        );
+
+        let mut message = message;
+        message.frame.len = 4;
+        assert!(serde_json::to_string(&message).is_err());
+
+        // a huge length with nothing behind it fails without allocating it
+        let huge = (1u64 << 45).to_le_bytes();
+        let mut input = vec![0; 4];
+        input.extend_from_slice(&huge);
+        assert!(bincode::deserialize::<Message>(&input).is_err());
+    }
+
+    #[test]
+    fn untrusted_size_hint() {
+        use ::serde::de::value::{Error, SeqDeserializer};
+
+        struct Lying(std::vec::IntoIter<u8>);
+
+        impl Iterator for Lying {
+            type Item = u8;
+            fn next(&mut self) -> Option<u8> {
+                self.0.next()
+            }
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                (1 << 45, Some(1 << 45))
+            }
+        }
+
+        let seq = SeqDeserializer::<_, Error>::new(Lying(vec![1, 2, 3].into_iter()));
+        let trailer = TailSeed(0u8).deserialize(seq).unwrap();
+        assert_eq!(trailer.bytes(), &[1, 2, 3]);
+    }
+}
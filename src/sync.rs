@@ -0,0 +1,389 @@
+//! thread-safe reference-counted trailers
+
+use std::{
+    fmt, hint,
+    marker::PhantomData,
+    mem, process, ptr, slice,
+    sync::atomic::{self, AtomicUsize, Ordering},
+};
+
+use crate::Trailer;
+
+const MAX_REFCOUNT: usize = isize::MAX as usize;
+
+/// header of the allocation: the counts are stored in front of `T`, and the
+/// tail follows it like in a `Trailer<ArcInner<T>>`
+#[repr(C)]
+struct ArcInner<T> {
+    strong: AtomicUsize,
+    // all the strong references together hold one weak reference
+    weak: AtomicUsize,
+    header: T,
+}
+
+/// a reference-counted trailer, with the counts, the header and the tail in
+/// one allocation
+///
+/// cloning it only increments the strong count, and the tail can only be
+/// modified while there is a single reference
+pub struct ArcTrailer<T> {
+    ptr: *mut u8,
+    capacity: usize,
+    phantom: PhantomData<ArcInner<T>>,
+}
+
+/// a non owning reference to an `ArcTrailer`
+pub struct Weak<T> {
+    ptr: *mut u8,
+    capacity: usize,
+    phantom: PhantomData<ArcInner<T>>,
+}
+
+unsafe impl<T: Send + Sync> Send for ArcTrailer<T> {}
+unsafe impl<T: Send + Sync> Sync for ArcTrailer<T> {}
+unsafe impl<T: Send + Sync> Send for Weak<T> {}
+unsafe impl<T: Send + Sync> Sync for Weak<T> {}
+
+impl<T> ArcTrailer<T> {
+    pub fn with_header(t: T, capacity: usize) -> ArcTrailer<T> {
+        ArcTrailer::new_with(capacity, |_| t)
+    }
+
+    /// fills the zeroed tail first, then builds the header from it
+    pub fn new_with<F: FnOnce(&mut [u8]) -> T>(capacity: usize, f: F) -> ArcTrailer<T> {
+        let trailer = mem::ManuallyDrop::new(Trailer::new_with(capacity, |bytes| ArcInner {
+            strong: AtomicUsize::new(1),
+            weak: AtomicUsize::new(1),
+            header: f(bytes),
+        }));
+
+        ArcTrailer {
+            ptr: trailer.ptr.as_ptr(),
+            capacity: trailer.capacity,
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.tail_ptr(), self.capacity) }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn strong_count(this: &Self) -> usize {
+        this.inner().strong.load(Ordering::Acquire)
+    }
+
+    pub fn weak_count(this: &Self) -> usize {
+        match this.inner().weak.load(Ordering::Acquire) {
+            // locked by `is_unique`, so there was no other weak reference
+            usize::MAX => 0,
+            count => count - 1,
+        }
+    }
+
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        this.ptr == other.ptr
+    }
+
+    pub fn downgrade(this: &Self) -> Weak<T> {
+        let weak = &this.inner().weak;
+        let mut current = weak.load(Ordering::Relaxed);
+        loop {
+            // `is_unique` is checking the counts
+            if current == usize::MAX {
+                hint::spin_loop();
+                current = weak.load(Ordering::Relaxed);
+                continue;
+            }
+
+            if current > MAX_REFCOUNT {
+                process::abort();
+            }
+
+            match weak.compare_exchange_weak(
+                current,
+                current + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => {
+                    return Weak {
+                        ptr: this.ptr,
+                        capacity: this.capacity,
+                        phantom: PhantomData,
+                    }
+                }
+                Err(old) => current = old,
+            }
+        }
+    }
+
+    /// mutable access to the header and the tail, if there is no other
+    /// strong or weak reference
+    pub fn get_mut(this: &mut Self) -> Option<(&mut T, &mut [u8])> {
+        if this.is_unique() {
+            Some(unsafe { this.parts_mut_unchecked() })
+        } else {
+            None
+        }
+    }
+
+    fn is_unique(&mut self) -> bool {
+        // locking the weak count prevents a concurrent `downgrade` followed by
+        // an `upgrade` while the strong count is checked
+        if self
+            .inner()
+            .weak
+            .compare_exchange(1, usize::MAX, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+        {
+            let unique = self.inner().strong.load(Ordering::Acquire) == 1;
+            self.inner().weak.store(1, Ordering::Release);
+            unique
+        } else {
+            false
+        }
+    }
+
+    unsafe fn parts_mut_unchecked(&mut self) -> (&mut T, &mut [u8]) {
+        let header = &mut (*(self.ptr as *mut ArcInner<T>)).header;
+        let bytes = slice::from_raw_parts_mut(self.tail_ptr(), self.capacity);
+        (header, bytes)
+    }
+
+    fn inner(&self) -> &ArcInner<T> {
+        unsafe { &*(self.ptr as *const ArcInner<T>) }
+    }
+
+    fn tail_ptr(&self) -> *mut u8 {
+        unsafe { self.ptr.add(Trailer::<ArcInner<T>>::tail_offset()) }
+    }
+}
+
+impl<T: Clone> ArcTrailer<T> {
+    /// mutable access to the header and the tail, copying them to a new
+    /// allocation first if they are shared
+    pub fn make_mut(this: &mut Self) -> (&mut T, &mut [u8]) {
+        if !this.is_unique() {
+            let bytes = this.bytes();
+            *this = ArcTrailer::new_with(this.capacity, |tail| {
+                tail.copy_from_slice(bytes);
+                (**this).clone()
+            });
+        }
+
+        unsafe { this.parts_mut_unchecked() }
+    }
+}
+
+impl<T> Clone for ArcTrailer<T> {
+    fn clone(&self) -> Self {
+        if self.inner().strong.fetch_add(1, Ordering::Relaxed) > MAX_REFCOUNT {
+            process::abort();
+        }
+
+        ArcTrailer {
+            ptr: self.ptr,
+            capacity: self.capacity,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> std::ops::Deref for ArcTrailer<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.inner().header
+    }
+}
+
+impl<T> Drop for ArcTrailer<T> {
+    fn drop(&mut self) {
+        if self.inner().strong.fetch_sub(1, Ordering::Release) != 1 {
+            return;
+        }
+
+        atomic::fence(Ordering::Acquire);
+        unsafe { ptr::drop_in_place(&mut (*(self.ptr as *mut ArcInner<T>)).header) };
+
+        // releases the weak reference held by the strong ones
+        drop(Weak::<T> {
+            ptr: self.ptr,
+            capacity: self.capacity,
+            phantom: PhantomData,
+        });
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ArcTrailer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ArcTrailer")
+            .field("header", &**self)
+            .field("bytes", &self.bytes())
+            .finish()
+    }
+}
+
+impl<T> Weak<T> {
+    /// gets a strong reference back if the trailer was not dropped yet
+    pub fn upgrade(&self) -> Option<ArcTrailer<T>> {
+        let strong = &self.inner().strong;
+        let mut current = strong.load(Ordering::Relaxed);
+        loop {
+            if current == 0 {
+                return None;
+            }
+
+            if current > MAX_REFCOUNT {
+                process::abort();
+            }
+
+            match strong.compare_exchange_weak(
+                current,
+                current + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => {
+                    return Some(ArcTrailer {
+                        ptr: self.ptr,
+                        capacity: self.capacity,
+                        phantom: PhantomData,
+                    })
+                }
+                Err(old) => current = old,
+            }
+        }
+    }
+
+    pub fn strong_count(&self) -> usize {
+        self.inner().strong.load(Ordering::Acquire)
+    }
+
+    // the counts stay valid as long as there is a weak reference, but not the
+    // header
+    fn inner(&self) -> &ArcInner<()> {
+        unsafe { &*(self.ptr as *const ArcInner<()>) }
+    }
+}
+
+impl<T> Clone for Weak<T> {
+    fn clone(&self) -> Self {
+        if self.inner().weak.fetch_add(1, Ordering::Relaxed) > MAX_REFCOUNT {
+            process::abort();
+        }
+
+        Weak {
+            ptr: self.ptr,
+            capacity: self.capacity,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> Drop for Weak<T> {
+    fn drop(&mut self) {
+        if self.inner().weak.fetch_sub(1, Ordering::Release) != 1 {
+            return;
+        }
+
+        atomic::fence(Ordering::Acquire);
+        unsafe { Trailer::<ArcInner<T>>::deallocate(self.ptr, self.capacity) };
+    }
+}
+
+impl<T> fmt::Debug for Weak<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "(Weak)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Inner {
+        name: String,
+    }
+
+    #[test]
+    fn shared() {
+        let a = ArcTrailer::new_with(4, |bytes| {
+            bytes.copy_from_slice(&[1, 2, 3, 4]);
+            Inner {
+                name: "a".to_string(),
+            }
+        });
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let a = a.clone();
+                thread::spawn(move || {
+                    assert_eq!(a.name, "a");
+                    a.bytes().iter().map(|b| *b as usize).sum::<usize>()
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), 10);
+        }
+        assert_eq!(ArcTrailer::strong_count(&a), 1);
+    }
+
+    #[test]
+    fn weak() {
+        let mut a = ArcTrailer::with_header(
+            Inner {
+                name: "a".to_string(),
+            },
+            2,
+        );
+        let weak = ArcTrailer::downgrade(&a);
+        assert_eq!(ArcTrailer::weak_count(&a), 1);
+        assert!(ArcTrailer::get_mut(&mut a).is_none());
+
+        let b = weak.upgrade().unwrap();
+        assert!(ArcTrailer::ptr_eq(&a, &b));
+        assert_eq!(ArcTrailer::strong_count(&a), 2);
+
+        drop(a);
+        drop(b);
+        assert_eq!(weak.strong_count(), 0);
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn get_mut_and_make_mut() {
+        let mut a = ArcTrailer::with_header(
+            Inner {
+                name: "a".to_string(),
+            },
+            2,
+        );
+        {
+            let (header, bytes) = ArcTrailer::get_mut(&mut a).unwrap();
+            header.name.push('b');
+            bytes[0] = 1;
+        }
+        assert_eq!(a.name, "ab");
+
+        let b = a.clone();
+        assert!(ArcTrailer::get_mut(&mut a).is_none());
+        {
+            let (header, bytes) = ArcTrailer::make_mut(&mut a);
+            header.name.push('c');
+            bytes[1] = 2;
+        }
+        assert!(!ArcTrailer::ptr_eq(&a, &b));
+        assert_eq!(a.name, "abc");
+        assert_eq!(a.bytes(), &[1, 2]);
+        assert_eq!(b.name, "ab");
+        assert_eq!(b.bytes(), &[1, 0]);
+        assert_eq!(ArcTrailer::strong_count(&b), 1);
+    }
+}
@@ -0,0 +1,141 @@
+use std::{
+    marker::PhantomData,
+    mem,
+    ops::{Deref, DerefMut},
+    ptr::NonNull,
+    slice,
+};
+
+use crate::{allocator::Global, Trailer};
+
+/// a `Trailer` that is a single pointer wide
+///
+/// the capacity is read from the prefix of the allocation instead of being
+/// stored next to the pointer, and the conversions from and to `Trailer` do
+/// not copy or reallocate anything
+#[derive(Debug)]
+pub struct ThinTrailer<T, U = u8> {
+    ptr: NonNull<u8>,
+    phantom: PhantomData<(T, U)>,
+}
+
+unsafe impl<T: Send, U: Send> Send for ThinTrailer<T, U> {}
+unsafe impl<T: Sync, U: Sync> Sync for ThinTrailer<T, U> {}
+
+impl<T, U> ThinTrailer<T, U> {
+    /// number of `U` elements in the tail
+    pub fn capacity(&self) -> usize {
+        unsafe { Trailer::<T, U>::stored_capacity(self.ptr.as_ptr()) }
+    }
+
+    pub fn as_slice(&self) -> &[U] {
+        unsafe { slice::from_raw_parts(self.tail_ptr(), self.capacity()) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [U] {
+        unsafe { slice::from_raw_parts_mut(self.tail_ptr(), self.capacity()) }
+    }
+
+    pub fn into_trailer(self) -> Trailer<T, U> {
+        let this = mem::ManuallyDrop::new(self);
+        Trailer {
+            ptr: this.ptr,
+            capacity: this.capacity(),
+            phantom: PhantomData,
+            alloc: Global,
+        }
+    }
+
+    fn tail_ptr(&self) -> *mut U {
+        unsafe { self.ptr.as_ptr().add(Trailer::<T, U>::tail_offset()) as *mut U }
+    }
+}
+
+impl<T> ThinTrailer<T> {
+    pub fn bytes(&self) -> &[u8] {
+        self.as_slice()
+    }
+
+    pub fn bytes_mut(&mut self) -> &mut [u8] {
+        self.as_mut_slice()
+    }
+}
+
+impl<T, U> From<Trailer<T, U>> for ThinTrailer<T, U> {
+    fn from(trailer: Trailer<T, U>) -> Self {
+        let trailer = mem::ManuallyDrop::new(trailer);
+        ThinTrailer {
+            ptr: trailer.ptr,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T, U> From<ThinTrailer<T, U>> for Trailer<T, U> {
+    fn from(thin: ThinTrailer<T, U>) -> Self {
+        thin.into_trailer()
+    }
+}
+
+impl<T, U> Drop for ThinTrailer<T, U> {
+    fn drop(&mut self) {
+        drop(Trailer::<T, U> {
+            ptr: self.ptr,
+            capacity: self.capacity(),
+            phantom: PhantomData,
+            alloc: Global,
+        });
+    }
+}
+
+impl<T, U> Deref for ThinTrailer<T, U> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*(self.ptr.as_ptr() as *const T) }
+    }
+}
+
+impl<T, U> DerefMut for ThinTrailer<T, U> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *(self.ptr.as_ptr() as *mut T) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thin() {
+        #[derive(Debug, Default)]
+        struct Inner {
+            field1: usize,
+        }
+
+        assert_eq!(mem::size_of::<ThinTrailer<Inner>>(), 8);
+        assert_eq!(mem::size_of::<Option<ThinTrailer<Inner>>>(), 8);
+
+        let mut a = Trailer::<Inner>::new(4);
+        a.field1 = 42;
+        a.bytes_mut().copy_from_slice(&[1, 2, 3, 4]);
+        a.resize(6);
+        let header = &*a as *const Inner;
+
+        let mut thin = ThinTrailer::from(a);
+        assert_eq!(&*thin as *const Inner, header);
+        assert_eq!(thin.capacity(), 6);
+        assert_eq!(thin.field1, 42);
+        assert_eq!(thin.bytes(), &[1, 2, 3, 4, 0, 0]);
+        thin.bytes_mut()[5] = 6;
+        thin.field1 += 1;
+
+        let a: Trailer<Inner> = thin.into();
+        assert_eq!(&*a as *const Inner, header);
+        assert_eq!(a.capacity(), 6);
+        assert_eq!(a.field1, 43);
+        assert_eq!(a.bytes(), &[1, 2, 3, 4, 0, 6]);
+
+        let typed = ThinTrailer::from(Trailer::<Inner, u64>::from_slice(Inner::default(), &[7]));
+        assert_eq!(typed.as_slice(), &[7]);
+    }
+}
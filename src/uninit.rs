@@ -0,0 +1,185 @@
+use std::{
+    cmp,
+    marker::PhantomData,
+    mem::{self, MaybeUninit},
+    ptr::{self, NonNull},
+    slice,
+};
+
+use crate::{allocator::Global, Trailer, TrailerAllocError};
+
+/// a trailer allocation whose header and tail are not initialized yet
+///
+/// it is created without zeroing the memory, and turns into a `Trailer<T>`
+/// through `assume_init` or `init_with`
+#[derive(Debug)]
+pub struct UninitTrailer<T> {
+    ptr: *mut u8,
+    capacity: usize,
+    phantom: PhantomData<T>,
+}
+
+impl<T> Trailer<T> {
+    /// allocates a trailer without zeroing it
+    pub fn new_uninit(capacity: usize) -> UninitTrailer<T> {
+        Trailer::try_new_uninit(capacity).unwrap_or_else(|e| e.handle())
+    }
+
+    pub fn try_new_uninit(capacity: usize) -> Result<UninitTrailer<T>, TrailerAllocError> {
+        let trailer =
+            mem::ManuallyDrop::new(unsafe { Trailer::<T>::try_allocate_uninit(capacity)? });
+        Ok(UninitTrailer {
+            ptr: trailer.ptr.as_ptr(),
+            capacity: trailer.capacity,
+            phantom: PhantomData,
+        })
+    }
+}
+
+unsafe impl<T: Send> Send for UninitTrailer<T> {}
+unsafe impl<T: Sync> Sync for UninitTrailer<T> {}
+
+impl<T> UninitTrailer<T> {
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn header_mut(&mut self) -> &mut MaybeUninit<T> {
+        unsafe { &mut *(self.ptr as *mut MaybeUninit<T>) }
+    }
+
+    pub fn bytes_mut(&mut self) -> &mut [MaybeUninit<u8>] {
+        unsafe {
+            slice::from_raw_parts_mut(
+                self.ptr.add(Trailer::<T>::tail_offset()) as *mut MaybeUninit<u8>,
+                self.capacity,
+            )
+        }
+    }
+
+    /// # Safety
+    ///
+    /// the header and every byte of the tail must have been initialized
+    /// through `header_mut()` and `bytes_mut()`
+    pub unsafe fn assume_init(self) -> Trailer<T> {
+        let this = mem::ManuallyDrop::new(self);
+        Trailer {
+            ptr: NonNull::new_unchecked(this.ptr),
+            capacity: this.capacity,
+            phantom: PhantomData,
+            alloc: Global,
+        }
+    }
+
+    /// fills the tail through `f`, which returns the header
+    ///
+    /// the bytes `f` did not write are zeroed, so only the unwritten part of
+    /// the tail pays for a memset
+    pub fn init_with<F: FnOnce(&mut UninitBytes) -> T>(mut self, f: F) -> Trailer<T> {
+        let mut bytes = UninitBytes {
+            buf: self.bytes_mut(),
+            filled: 0,
+        };
+        let t = f(&mut bytes);
+        let filled = bytes.filled;
+
+        unsafe {
+            let spare = self.bytes_mut()[filled..].as_mut_ptr();
+            ptr::write_bytes(spare, 0, self.capacity - filled);
+            self.header_mut().as_mut_ptr().write(t);
+            self.assume_init()
+        }
+    }
+}
+
+impl<T> Drop for UninitTrailer<T> {
+    fn drop(&mut self) {
+        unsafe { Trailer::<T>::deallocate(self.ptr, self.capacity) };
+    }
+}
+
+/// the tail of an `UninitTrailer`, filled from the start
+#[derive(Debug)]
+pub struct UninitBytes<'a> {
+    buf: &'a mut [MaybeUninit<u8>],
+    filled: usize,
+}
+
+impl<'a> UninitBytes<'a> {
+    /// the bytes written so far
+    pub fn filled(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.buf.as_ptr() as *const u8, self.filled) }
+    }
+
+    /// the bytes that were not written yet
+    pub fn spare_mut(&mut self) -> &mut [MaybeUninit<u8>] {
+        &mut self.buf[self.filled..]
+    }
+
+    /// copies as much of `data` as fits, returning the number of bytes written
+    pub fn append(&mut self, data: &[u8]) -> usize {
+        let count = cmp::min(data.len(), self.buf.len() - self.filled);
+        unsafe {
+            ptr::copy_nonoverlapping(
+                data.as_ptr(),
+                self.spare_mut().as_mut_ptr() as *mut u8,
+                count,
+            )
+        };
+        self.filled += count;
+        count
+    }
+
+    /// marks the next `n` bytes as written
+    ///
+    /// # Safety
+    ///
+    /// the first `n` bytes of `spare_mut()` must have been initialized
+    pub unsafe fn advance(&mut self, n: usize) {
+        debug_assert!(n <= self.buf.len() - self.filled);
+        self.filled += n;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uninit() {
+        #[derive(Debug)]
+        struct Inner {
+            len: usize,
+        }
+
+        let mut a = Trailer::<Inner>::new_uninit(4);
+        assert_eq!(a.capacity(), 4);
+        a.header_mut().write(Inner { len: 4 });
+        for (i, byte) in a.bytes_mut().iter_mut().enumerate() {
+            byte.write(i as u8);
+        }
+        let a = unsafe { a.assume_init() };
+        assert_eq!(a.len, 4);
+        assert_eq!(a.bytes(), &[0, 1, 2, 3]);
+
+        let b = Trailer::<Inner>::new_uninit(6).init_with(|bytes| {
+            assert_eq!(bytes.append(&[1, 2, 3]), 3);
+            bytes.spare_mut()[0].write(4);
+            unsafe { bytes.advance(1) };
+            assert_eq!(bytes.filled(), &[1, 2, 3, 4]);
+            Inner {
+                len: bytes.filled().len(),
+            }
+        });
+        assert_eq!(b.len, 4);
+        assert_eq!(b.bytes(), &[1, 2, 3, 4, 0, 0]);
+
+        let c = Trailer::<Inner>::new_uninit(2).init_with(|bytes| Inner {
+            len: bytes.append(&[1, 2, 3]),
+        });
+        assert_eq!(c.len, 2);
+        assert_eq!(c.bytes(), &[1, 2]);
+
+        drop(Trailer::<Inner>::new_uninit(8));
+    }
+}
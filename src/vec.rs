@@ -0,0 +1,191 @@
+use std::{
+    cmp,
+    mem::MaybeUninit,
+    ops::{Deref, DerefMut},
+    ptr, slice,
+};
+
+use crate::{Trailer, TrailerAllocError};
+
+/// a `Trailer` that tracks how many bytes of its tail are initialized
+///
+/// `Deref` gives access to the header, while `bytes()` only returns the
+/// `len()` bytes that were pushed so far
+#[derive(Debug)]
+pub struct TrailerVec<T> {
+    trailer: Trailer<T>,
+    len: usize,
+}
+
+impl<T: Default> TrailerVec<T> {
+    pub fn new(capacity: usize) -> TrailerVec<T> {
+        TrailerVec::from_trailer(Trailer::new(capacity))
+    }
+
+    pub fn try_with_capacity(capacity: usize) -> Result<TrailerVec<T>, TrailerAllocError> {
+        Trailer::try_new(capacity).map(TrailerVec::from_trailer)
+    }
+}
+
+impl<T> TrailerVec<T> {
+    /// wraps an existing trailer, starting with a length of 0
+    pub fn from_trailer(trailer: Trailer<T>) -> TrailerVec<T> {
+        TrailerVec { trailer, len: 0 }
+    }
+
+    /// converts back to a `Trailer`, shrinking its capacity to `len()`
+    pub fn into_trailer(mut self) -> Trailer<T> {
+        unsafe { self.trailer.resize_uninit(self.len) };
+        self.trailer
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.trailer.capacity()
+    }
+
+    /// the initialized part of the tail
+    pub fn bytes(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.tail_ptr(), self.len) }
+    }
+
+    pub fn bytes_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.tail_ptr(), self.len) }
+    }
+
+    /// the part of the tail after `len()`
+    pub fn spare_capacity_mut(&mut self) -> &mut [MaybeUninit<u8>] {
+        unsafe {
+            slice::from_raw_parts_mut(
+                self.tail_ptr().add(self.len) as *mut MaybeUninit<u8>,
+                self.capacity() - self.len,
+            )
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `new_len` must be lower or equal to `capacity()` and the bytes up to
+    /// `new_len` must be initialized
+    pub unsafe fn set_len(&mut self, new_len: usize) {
+        debug_assert!(new_len <= self.capacity());
+        self.len = new_len;
+    }
+
+    /// makes sure at least `additional` bytes can be pushed without
+    /// reallocating
+    pub fn reserve(&mut self, additional: usize) {
+        self.try_reserve(additional).unwrap_or_else(|e| e.handle())
+    }
+
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TrailerAllocError> {
+        let required = self
+            .len
+            .checked_add(additional)
+            .ok_or(TrailerAllocError::CapacityOverflow)?;
+        if required <= self.capacity() {
+            return Ok(());
+        }
+
+        let new_capacity = cmp::max(cmp::max(self.capacity().saturating_mul(2), required), 8);
+        unsafe { self.trailer.try_realloc(new_capacity) }
+    }
+
+    pub fn push(&mut self, byte: u8) {
+        if self.len == self.capacity() {
+            self.reserve(1);
+        }
+
+        unsafe { self.tail_ptr().add(self.len).write(byte) };
+        self.len += 1;
+    }
+
+    pub fn extend_from_slice(&mut self, data: &[u8]) {
+        self.reserve(data.len());
+
+        unsafe {
+            ptr::copy_nonoverlapping(data.as_ptr(), self.tail_ptr().add(self.len), data.len())
+        };
+        self.len += data.len();
+    }
+
+    /// shortens the initialized part to `len` bytes, keeping the capacity
+    pub fn truncate(&mut self, len: usize) {
+        self.len = cmp::min(self.len, len);
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    fn tail_ptr(&self) -> *mut u8 {
+        self.trailer.tail_ptr()
+    }
+}
+
+impl<T> Deref for TrailerVec<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.trailer
+    }
+}
+
+impl<T> DerefMut for TrailerVec<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.trailer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_extend_truncate() {
+        #[derive(Debug, Default)]
+        struct Inner {
+            used: usize,
+        }
+
+        let mut v = TrailerVec::<Inner>::new(2);
+        assert!(v.is_empty());
+        assert_eq!(v.capacity(), 2);
+
+        v.push(1);
+        v.push(2);
+        v.push(3);
+        v.used = v.len();
+        assert_eq!(v.bytes(), &[1, 2, 3]);
+        assert_eq!(v.used, 3);
+        assert!(v.capacity() >= 3);
+
+        v.extend_from_slice(&[4, 5, 6, 7, 8, 9, 10]);
+        assert_eq!(v.len(), 10);
+        assert_eq!(v.bytes(), &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+        assert_eq!(v.used, 3);
+
+        v.truncate(4);
+        assert_eq!(v.bytes(), &[1, 2, 3, 4]);
+        assert_eq!(v.spare_capacity_mut().len(), v.capacity() - 4);
+
+        v.spare_capacity_mut()[0] = MaybeUninit::new(42);
+        unsafe { v.set_len(5) };
+        assert_eq!(v.bytes(), &[1, 2, 3, 4, 42]);
+
+        let t = v.into_trailer();
+        assert_eq!(t.capacity(), 5);
+        assert_eq!(t.bytes(), &[1, 2, 3, 4, 42]);
+
+        let mut v = TrailerVec::from_trailer(t);
+        v.clear();
+        assert!(v.is_empty());
+        assert_eq!(v.capacity(), 5);
+    }
+}
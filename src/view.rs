@@ -0,0 +1,182 @@
+use std::{
+    fmt,
+    ops::{Deref, DerefMut},
+};
+
+use crate::{allocator::Allocator, ArenaTrailer, ThinTrailer, Trailer, TrailerVec};
+
+/// a borrowed header and tail, that can come from any kind of trailer
+pub struct TrailerRef<'a, T, U = u8> {
+    header: &'a T,
+    tail: &'a [U],
+}
+
+/// a mutably borrowed header and tail, that can come from any kind of
+/// trailer
+pub struct TrailerMut<'a, T, U = u8> {
+    header: &'a mut T,
+    tail: &'a mut [U],
+}
+
+impl<'a, T, U> TrailerRef<'a, T, U> {
+    pub fn new(header: &'a T, tail: &'a [U]) -> TrailerRef<'a, T, U> {
+        TrailerRef { header, tail }
+    }
+
+    pub fn header(&self) -> &'a T {
+        self.header
+    }
+
+    pub fn as_slice(&self) -> &'a [U] {
+        self.tail
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.tail.len()
+    }
+
+    pub fn parts(&self) -> (&'a T, &'a [U]) {
+        (self.header, self.tail)
+    }
+}
+
+impl<'a, T> TrailerRef<'a, T> {
+    pub fn bytes(&self) -> &'a [u8] {
+        self.tail
+    }
+}
+
+impl<'a, T, U> TrailerMut<'a, T, U> {
+    pub fn new(header: &'a mut T, tail: &'a mut [U]) -> TrailerMut<'a, T, U> {
+        TrailerMut { header, tail }
+    }
+
+    pub fn as_slice(&self) -> &[U] {
+        self.tail
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [U] {
+        self.tail
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.tail.len()
+    }
+
+    pub fn parts_mut(&mut self) -> (&mut T, &mut [U]) {
+        (self.header, self.tail)
+    }
+
+    pub fn into_parts(self) -> (&'a mut T, &'a mut [U]) {
+        (self.header, self.tail)
+    }
+
+    /// a shorter lived `TrailerMut`, so that this one can be used again later
+    pub fn reborrow(&mut self) -> TrailerMut<'_, T, U> {
+        TrailerMut {
+            header: self.header,
+            tail: self.tail,
+        }
+    }
+
+    pub fn as_ref(&self) -> TrailerRef<'_, T, U> {
+        TrailerRef {
+            header: self.header,
+            tail: self.tail,
+        }
+    }
+}
+
+impl<'a, T> TrailerMut<'a, T> {
+    pub fn bytes(&self) -> &[u8] {
+        self.tail
+    }
+
+    pub fn bytes_mut(&mut self) -> &mut [u8] {
+        self.tail
+    }
+}
+
+impl<'a, T, U> Clone for TrailerRef<'a, T, U> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T, U> Copy for TrailerRef<'a, T, U> {}
+
+impl<'a, T, U> Deref for TrailerRef<'a, T, U> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        self.header
+    }
+}
+
+impl<'a, T, U> Deref for TrailerMut<'a, T, U> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        self.header
+    }
+}
+
+impl<'a, T, U> DerefMut for TrailerMut<'a, T, U> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.header
+    }
+}
+
+impl<'a, T: fmt::Debug, U: fmt::Debug> fmt::Debug for TrailerRef<'a, T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TrailerRef")
+            .field("header", &self.header)
+            .field("tail", &self.tail)
+            .finish()
+    }
+}
+
+impl<'a, T: fmt::Debug, U: fmt::Debug> fmt::Debug for TrailerMut<'a, T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TrailerMut")
+            .field("header", &self.header)
+            .field("tail", &self.tail)
+            .finish()
+    }
+}
+
+impl<'a, T, U, A: Allocator> From<&'a Trailer<T, U, A>> for TrailerRef<'a, T, U> {
+    fn from(trailer: &'a Trailer<T, U, A>) -> Self {
+        trailer.view()
+    }
+}
+
+impl<'a, T, U, A: Allocator> From<&'a mut Trailer<T, U, A>> for TrailerMut<'a, T, U> {
+    fn from(trailer: &'a mut Trailer<T, U, A>) -> Self {
+        trailer.view_mut()
+    }
+}
+
+impl<'a, T, U> From<&'a ThinTrailer<T, U>> for TrailerRef<'a, T, U> {
+    fn from(trailer: &'a ThinTrailer<T, U>) -> Self {
+        TrailerRef::new(trailer, trailer.as_slice())
+    }
+}
+
+impl<'a, T> From<&'a ArenaTrailer<T>> for TrailerRef<'a, T> {
+    fn from(trailer: &'a ArenaTrailer<T>) -> Self {
+        TrailerRef::new(trailer, trailer.bytes())
+    }
+}
+
+impl<'a, T> From<&'a mut ArenaTrailer<T>> for TrailerMut<'a, T> {
+    fn from(trailer: &'a mut ArenaTrailer<T>) -> Self {
+        let (header, bytes) = trailer.parts_mut();
+        TrailerMut::new(header, bytes)
+    }
+}
+
+/// only the initialized bytes are part of the view
+impl<'a, T> From<&'a TrailerVec<T>> for TrailerRef<'a, T> {
+    fn from(trailer: &'a TrailerVec<T>) -> Self {
+        TrailerRef::new(trailer, trailer.bytes())
+    }
+}
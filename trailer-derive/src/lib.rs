@@ -0,0 +1,347 @@
+//! `#[derive(Trailer)]` for the header structs of the `trailer` crate
+//!
+//! the header declares the layout of its tail through length fields:
+//!
+//! - `#[trailer(len)]` on a field marks it as the length of the whole payload
+//! - `#[trailer(region = "name", len = "field")]` on the struct declares a
+//!   region of the tail, whose length is held by `field`. Regions are laid
+//!   out one after the other, in the order they are declared
+//!
+//! the derive implements `trailer::TrailerHeader`, and generates an
+//! `into_trailer` constructor taking one slice per region (or the whole
+//! payload if there are no regions). With regions, it also generates a
+//! `<Name>Regions` trait implemented for `Trailer<Name>`, with one
+//! accessor pair per region and a `validate_regions` method
+//!
+//! the accessors panic if the length fields were changed so that a region
+//! ends past the capacity, `validate_regions` reports that case as an error
+//!
+//! ```
+//! use trailer::{RegionError, Trailer};
+//!
+//! #[derive(Trailer)]
+//! #[trailer(region = "name", len = "name_len")]
+//! #[trailer(region = "data", len = "data_len")]
+//! pub struct Packet {
+//!     kind: u8,
+//!     name_len: u8,
+//!     data_len: u32,
+//! }
+//!
+//! # fn main() -> Result<(), RegionError> {
+//! let mut packet = Packet { kind: 1, name_len: 3, data_len: 2 }
+//!     .into_trailer(b"abc", &[1, 2])?;
+//! assert_eq!(packet.kind, 1);
+//! assert_eq!(packet.name(), b"abc");
+//! assert_eq!(packet.data(), &[1, 2]);
+//!
+//! packet.data_len = 3;
+//! assert!(packet.validate_regions().is_err());
+//! # Ok(())
+//! # }
+//! ```
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident, LitStr};
+
+#[proc_macro_derive(Trailer, attributes(trailer))]
+pub fn derive_trailer(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+struct Region {
+    name: Ident,
+    len: Ident,
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "#[derive(Trailer)] needs a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "#[derive(Trailer)] can only be used on structs",
+            ))
+        }
+    };
+
+    let mut regions = Vec::new();
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("trailer")) {
+        let mut name = None;
+        let mut len = None;
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("region") {
+                name = Some(meta.value()?.parse::<LitStr>()?.parse::<Ident>()?);
+                Ok(())
+            } else if meta.path.is_ident("len") {
+                len = Some(meta.value()?.parse::<LitStr>()?.parse::<Ident>()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `region` or `len`"))
+            }
+        })?;
+
+        match (name, len) {
+            (Some(name), Some(len)) => {
+                if !fields.iter().any(|f| f.ident.as_ref() == Some(&len)) {
+                    return Err(syn::Error::new_spanned(
+                        &len,
+                        format!("no field named `{}`", len),
+                    ));
+                }
+                regions.push(Region { name, len });
+            }
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "expected #[trailer(region = \"name\", len = \"field\")]",
+                ))
+            }
+        }
+    }
+
+    let mut total = None;
+    for field in fields {
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("trailer")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("len") {
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `len`"))
+                }
+            })?;
+            if total.is_some() {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "only one field can be marked #[trailer(len)]",
+                ));
+            }
+            total = field.ident.clone();
+        }
+    }
+
+    if total.is_none() && regions.is_empty() {
+        return Err(syn::Error::new(
+            Span::call_site(),
+            "#[derive(Trailer)] needs a #[trailer(len)] field or a \
+             #[trailer(region = \"name\", len = \"field\")] attribute",
+        ));
+    }
+
+    let ident = &input.ident;
+    let vis = &input.vis;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let region_lens: Vec<_> = regions
+        .iter()
+        .map(|r| {
+            let len = &r.len;
+            quote!((self.#len as usize))
+        })
+        .collect();
+    let regions_len = quote!(0usize #(.saturating_add #region_lens)*);
+    let payload_len = match &total {
+        Some(total) => quote!(self.#total as usize),
+        None => regions_len.clone(),
+    };
+
+    // the data given to the constructor, one slice per region or the whole
+    // payload
+    let (args, checks, copies) = if regions.is_empty() {
+        let total = total.as_ref().unwrap();
+        let total_name = total.to_string();
+        (
+            vec![quote!(payload: &[u8])],
+            vec![quote! {
+                if self.#total as usize != payload.len() {
+                    return Err(::trailer::RegionError::LenMismatch {
+                        field: #total_name,
+                        len: self.#total as usize,
+                        data: payload.len(),
+                    });
+                }
+            }],
+            vec![quote!(trailer.bytes_mut().copy_from_slice(payload);)],
+        )
+    } else {
+        let mut args = Vec::new();
+        let mut checks = Vec::new();
+        let mut copies = Vec::new();
+        for region in &regions {
+            let name = &region.name;
+            let len = &region.len;
+            let len_name = len.to_string();
+            args.push(quote!(#name: &[u8]));
+            checks.push(quote! {
+                if self.#len as usize != #name.len() {
+                    return Err(::trailer::RegionError::LenMismatch {
+                        field: #len_name,
+                        len: self.#len as usize,
+                        data: #name.len(),
+                    });
+                }
+            });
+            copies.push(quote! {
+                trailer.bytes_mut()[offset..offset + #name.len()].copy_from_slice(#name);
+                offset += #name.len();
+            });
+        }
+        if let Some(total) = &total {
+            let total_name = total.to_string();
+            checks.push(quote! {
+                if self.#total as usize != #regions_len {
+                    return Err(::trailer::RegionError::LenMismatch {
+                        field: #total_name,
+                        len: self.#total as usize,
+                        data: #regions_len,
+                    });
+                }
+            });
+        }
+        (args, checks, copies)
+    };
+
+    let constructor_doc = if regions.is_empty() {
+        "allocates a trailer holding this header and `payload`, after checking \
+         that the length field matches it"
+            .to_string()
+    } else {
+        format!(
+            "allocates a trailer holding this header and the regions {}, after \
+             checking that the length fields match them",
+            regions
+                .iter()
+                .map(|r| format!("`{}`", r.name))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    };
+
+    let header_impls = quote! {
+        impl #impl_generics ::trailer::TrailerHeader for #ident #ty_generics #where_clause {
+            fn payload_len(&self) -> usize {
+                #payload_len
+            }
+        }
+
+        impl #impl_generics #ident #ty_generics #where_clause {
+            #[doc = #constructor_doc]
+            #vis fn into_trailer(
+                self,
+                #(#args),*
+            ) -> ::std::result::Result<::trailer::Trailer<Self>, ::trailer::RegionError> {
+                #(#checks)*
+                let capacity = ::trailer::TrailerHeader::payload_len(&self);
+                #[allow(unused_mut, unused_variables)]
+                let mut offset = 0usize;
+                let mut trailer = ::trailer::Trailer::with_header(self, capacity);
+                #(#copies)*
+                Ok(trailer)
+            }
+        }
+    };
+
+    if regions.is_empty() {
+        return Ok(header_impls);
+    }
+
+    let trait_ident = format_ident!("{}Regions", ident);
+    let trait_doc = format!("accessors for the tail regions declared by `{}`", ident);
+    let mut signatures = Vec::new();
+    let mut accessors = Vec::new();
+    for (i, region) in regions.iter().enumerate() {
+        let name = &region.name;
+        let name_mut = format_ident!("{}_mut", name);
+        let doc = format!("the `{}` region of the tail", name);
+        let doc_mut = format!("the `{}` region of the tail, mutably borrowed", name);
+        let panics = "# Panics\n\n\
+                      panics if the region ends past the capacity, which can happen if a \
+                      length field was changed after allocation. `validate_regions` checks \
+                      every region beforehand";
+        signatures.push(quote! {
+            #[doc = #doc]
+            #[doc = ""]
+            #[doc = #panics]
+            fn #name(&self) -> &[u8];
+            #[doc = #doc_mut]
+            #[doc = ""]
+            #[doc = #panics]
+            fn #name_mut(&mut self) -> &mut [u8];
+        });
+        accessors.push(quote! {
+            fn #name(&self) -> &[u8] {
+                let (start, end) = #ident::__trailer_region(self, #i);
+                &self.bytes()[start..end]
+            }
+
+            fn #name_mut(&mut self) -> &mut [u8] {
+                let (start, end) = #ident::__trailer_region(self, #i);
+                &mut self.bytes_mut()[start..end]
+            }
+        });
+    }
+
+    let region_arms = regions.iter().enumerate().map(|(i, region)| {
+        let len = &region.len;
+        let previous = &region_lens[..i];
+        quote! {
+            #i => {
+                let start = 0usize #(.saturating_add #previous)*;
+                (start, start.saturating_add(self.#len as usize))
+            }
+        }
+    });
+
+    Ok(quote! {
+        #header_impls
+
+        impl #impl_generics #ident #ty_generics #where_clause {
+            #[doc(hidden)]
+            fn __trailer_region(&self, index: usize) -> (usize, usize) {
+                match index {
+                    #(#region_arms)*
+                    _ => unreachable!(),
+                }
+            }
+        }
+
+        #[doc = #trait_doc]
+        #vis trait #trait_ident {
+            #(#signatures)*
+
+            /// checks that every region fits in the tail
+            fn validate_regions(&self) -> ::std::result::Result<(), ::trailer::RegionError>;
+        }
+
+        impl #impl_generics #trait_ident for ::trailer::Trailer<#ident #ty_generics> #where_clause {
+            #(#accessors)*
+
+            fn validate_regions(&self) -> ::std::result::Result<(), ::trailer::RegionError> {
+                let header: &#ident #ty_generics = self;
+                let end = #regions_len;
+                let end = ::std::cmp::max(end, ::trailer::TrailerHeader::payload_len(header));
+                if end > self.capacity() {
+                    return Err(::trailer::RegionError::OutOfBounds {
+                        end,
+                        capacity: self.capacity(),
+                    });
+                }
+                Ok(())
+            }
+        }
+    })
+}
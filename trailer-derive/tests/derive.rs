@@ -0,0 +1,116 @@
+use trailer::{RegionError, Trailer, TrailerHeader};
+
+#[derive(Debug, Trailer)]
+#[trailer(region = "name", len = "name_len")]
+#[trailer(region = "data", len = "data_len")]
+pub struct Packet {
+    kind: u8,
+    name_len: u8,
+    data_len: u32,
+}
+
+#[derive(Debug, Trailer)]
+#[trailer(region = "key", len = "key_len")]
+struct Entry<T> {
+    value: T,
+    #[trailer(len)]
+    total: u16,
+    key_len: u16,
+}
+
+#[derive(Debug, Trailer)]
+struct Frame {
+    #[trailer(len)]
+    payload_len: usize,
+}
+
+#[test]
+fn regions() {
+    let mut packet = Packet {
+        kind: 1,
+        name_len: 3,
+        data_len: 2,
+    }
+    .into_trailer(b"abc", &[1, 2])
+    .unwrap();
+    assert_eq!(packet.kind, 1);
+    assert_eq!(packet.payload_len(), 5);
+    assert_eq!(packet.capacity(), 5);
+    assert_eq!(packet.name(), b"abc");
+    assert_eq!(packet.data(), &[1, 2]);
+    packet.data_mut()[1] = 3;
+    assert_eq!(packet.bytes(), b"abc\x01\x03");
+    assert_eq!(packet.validate_regions(), Ok(()));
+
+    packet.data_len = 3;
+    assert_eq!(
+        packet.validate_regions(),
+        Err(RegionError::OutOfBounds {
+            end: 6,
+            capacity: 5
+        })
+    );
+
+    let err = Packet {
+        kind: 1,
+        name_len: 2,
+        data_len: 2,
+    }
+    .into_trailer(b"abc", &[1, 2])
+    .unwrap_err();
+    assert_eq!(
+        err,
+        RegionError::LenMismatch {
+            field: "name_len",
+            len: 2,
+            data: 3
+        }
+    );
+}
+
+#[test]
+#[should_panic(expected = "out of range")]
+fn region_out_of_bounds() {
+    let mut packet = Packet {
+        kind: 1,
+        name_len: 1,
+        data_len: 1,
+    }
+    .into_trailer(b"a", b"b")
+    .unwrap();
+    packet.name_len = 2;
+    packet.data();
+}
+
+#[test]
+fn total_len() {
+    let entry = Entry {
+        value: "x",
+        total: 3,
+        key_len: 3,
+    }
+    .into_trailer(b"key")
+    .unwrap();
+    assert_eq!(entry.value, "x");
+    assert_eq!(entry.key(), b"key");
+
+    let err = Entry {
+        value: (),
+        total: 4,
+        key_len: 3,
+    }
+    .into_trailer(b"key")
+    .unwrap_err();
+    assert_eq!(
+        err,
+        RegionError::LenMismatch {
+            field: "total",
+            len: 4,
+            data: 3
+        }
+    );
+
+    let frame = Frame { payload_len: 2 }.into_trailer(&[1, 2]).unwrap();
+    assert_eq!(frame.bytes(), &[1, 2]);
+    assert!(Frame { payload_len: 2 }.into_trailer(&[1]).is_err());
+}